@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContext, EguiPlugin};
 use rand::SeedableRng;
-use wfc_solver::Solution;
+use wfc_solver::{explain::Explanation, validation::ValidationIssue, Solution};
 
 use crate::wfc_asset::WfcProblemResource;
 
@@ -11,10 +11,15 @@ struct UIState {
     error_message: Option<String>,
     random_seed: bool,
     seed: u64,
+    issues: Option<Vec<ValidationIssue<String>>>,
+    explanation: Option<Explanation<String>>,
+    /// The share of every tile in the last solution, when the description targets one.
+    histogram: Option<Vec<(String, f32)>>,
 }
 
+/// A solution waiting to be spawned, with the seed it was generated from.
 #[derive(Component)]
-pub struct LoadedSolution(pub Solution<String>);
+pub struct LoadedSolution(pub Solution<String>, pub u64);
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
@@ -24,6 +29,9 @@ impl Plugin for UIPlugin {
                 error_message: None,
                 random_seed: true,
                 seed: 0,
+                issues: None,
+                explanation: None,
+                histogram: None,
             });
     }
 }
@@ -40,6 +48,9 @@ fn gui_system(
             if let Some(error_message) = &ui_state.error_message {
                 ui.label(error_message);
             }
+            if let Some(explanation) = &ui_state.explanation {
+                ui.label(explanation.to_string());
+            }
 
             egui::Grid::new("Generation").show(ui, |ui| {
                 ui.end_row();
@@ -63,21 +74,60 @@ fn gui_system(
             ui.add(egui::DragValue::new(&mut ui_state.seed));
 
             if ui.button("Generate").clicked() {
-                let mut rng = if ui_state.random_seed {
-                    rand::rngs::SmallRng::seed_from_u64(
-                        ((time.seconds_since_startup() * 1000.0) % (std::u64::MAX as f64)) as u64,
-                    )
+                let seed = if ui_state.random_seed {
+                    ((time.seconds_since_startup() * 1000.0) % (std::u64::MAX as f64)) as u64
                 } else {
-                    rand::rngs::SmallRng::seed_from_u64(ui_state.seed)
+                    ui_state.seed
                 };
+                let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
                 match wfc_solver::solve(&mut rng, wfc.description.clone()) {
-                    Ok(solution) => {
-                        commands.spawn().insert(LoadedSolution(solution));
-                        ui_state.error_message = None;
+                    Ok(mut solution) => {
+                        ui_state.error_message = solution
+                            .rewrite(&wfc.rules)
+                            .err()
+                            .map(|err| format!("Failed rewriting! {}", err));
+                        ui_state.histogram = wfc.description.distribution.as_ref().map(|_| {
+                            solution
+                                .histogram()
+                                .into_iter()
+                                .map(|(id, share)| (id.clone(), share))
+                                .collect()
+                        });
+                        commands.spawn().insert(LoadedSolution(solution, seed));
+                        ui_state.explanation = None;
+                    }
+                    Err(err) => {
+                        ui_state.error_message = Some(format!("Failed solving! {}", err));
+                        ui_state.issues = Some(wfc.description.validate());
+                        ui_state.explanation = wfc.description.explain();
                     }
-                    Err(err) => ui_state.error_message = Some(format!("Failed solving! {}", err)),
                 }
             }
+
+            if let Some(histogram) = &ui_state.histogram {
+                egui::CollapsingHeader::new("Distribution").show(ui, |ui| {
+                    for (id, share) in histogram {
+                        ui.label(format!("{}: {:.1}%", id, share * 100.0));
+                    }
+                });
+            }
+
+            if ui.button("Validate").clicked() {
+                ui_state.issues = Some(wfc.description.validate());
+            }
+
+            if let Some(issues) = &ui_state.issues {
+                egui::CollapsingHeader::new(format!("Validation ({} issues)", issues.len()))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        if issues.is_empty() {
+                            ui.label("No problems found");
+                        }
+                        for issue in issues {
+                            ui.label(issue.to_string());
+                        }
+                    });
+            }
         });
     }
 }
@@ -1,17 +1,139 @@
 use bevy::{prelude::*, reflect::TypeUuid};
 use bevy_asset_ron::RonAssetPlugin;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer};
+use std::{collections::HashMap, fmt::Display};
 use wfc_solver::{
-    description::{ProblemDescription, Tile},
-    utils::Dimensions,
+    description::{
+        ConnectivityConstraint, CountConstraint, Distribution, HeightGuide, HorizontalConnection,
+        PathConstraint, Placement, Prefab, PrefabPlacement, ProblemDescription, Symmetry, Tile,
+        TileMetadata, TileRotation, TileSelector, VerticalConnection, WeightField, Zone, ZoneMap,
+    },
+    description2d::{ProblemDescription2d, Tile2d},
+    rewrite::RewriteRule,
+    utils::{Dimensions, Heightmap, Mask, Point, ZoneLayout},
 };
 
-#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[derive(Debug, Clone, Default, Deserialize, TypeUuid)]
 #[uuid = "1df82c01-9c71-4fa8-adc4-78c5822268f8"]
 pub struct TileAsset {
     pub scene: Option<TileMesh>,
-    pub tile: Tile<String>,
+    /// Alternative meshes for the tile, picked per cell next to `scene`. The solver only sees
+    /// the one logical tile.
+    #[serde(default)]
+    pub variants: Vec<VisualVariant>,
+    /// Image drawn for the tile in a 2D description.
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub sprite: Option<String>,
+    pub tile: TileDefinition,
+}
+
+/// A tile as written in a `.tile` file or as a template in a `.desc` file.
+///
+/// Every face is optional: a face that is left out is inherited from the template or tile
+/// named in `extends`. Faces can reference named socket profiles of the description.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TileDefinition {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub extends: Option<String>,
+
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub up: Option<Vec<SocketEntry<VerticalConnection>>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub down: Option<Vec<SocketEntry<VerticalConnection>>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub left: Option<Vec<SocketEntry<HorizontalConnection>>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub right: Option<Vec<SocketEntry<HorizontalConnection>>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub forward: Option<Vec<SocketEntry<HorizontalConnection>>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub backward: Option<Vec<SocketEntry<HorizontalConnection>>>,
+
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub can_rotate: Option<bool>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub can_flip: Option<bool>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub weight: Option<f32>,
+
+    #[serde(default)]
+    pub metadata: TileMetadata,
+    /// Added to the tags of the template.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub placement: Option<Placement>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub footprint: Option<(usize, usize, usize)>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    pub decorative: Option<bool>,
+}
+
+/// Either a socket written out in full, or the name of a socket profile.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SocketEntry<C> {
+    Profile(String),
+    Socket(C),
+}
+
+/// The character of `point` in layers written from the bottom up, with one string per row
+/// along z and one character per cell along x. The last layer is repeated up to the top.
+fn layout_cell(layers: &[Vec<String>], point: Point) -> Option<char> {
+    layers
+        .get(point.y())
+        .or_else(|| layers.last())?
+        .get(point.z())
+        .and_then(|row| row.chars().nth(point.x()))
+}
+
+fn parse_mask(layers: &[Vec<String>], dimensions: Dimensions) -> Option<Mask> {
+    layers.last()?;
+    Some(Mask::from_fn(
+        dimensions,
+        |point| matches!(layout_cell(layers, point), Some(cell) if cell != '.'),
+    ))
+}
+
+/// Zones are numbered in the order of their symbols. A cell with a symbol that is not a zone
+/// gets an index past the last zone, which fails the check of the description.
+fn parse_zones(source: &ZoneSource, dimensions: Dimensions) -> ZoneMap<String> {
+    let mut symbols = source.zones.keys().copied().collect::<Vec<_>>();
+    symbols.sort_unstable();
+    let layout = ZoneLayout::from_fn(dimensions, |point| {
+        layout_cell(&source.layout, point)
+            .and_then(|cell| symbols.iter().position(|symbol| *symbol == cell))
+            .unwrap_or(symbols.len())
+    });
+
+    ZoneMap::new(
+        symbols
+            .iter()
+            .map(|symbol| source.zones[symbol].clone())
+            .collect(),
+        layout,
+        source.transitions.clone(),
+    )
+}
+
+/// Reads the first channel of every pixel as the gray level.
+fn image_heightmap(image: &Image, max_height: usize) -> Heightmap {
+    let size = image.texture_descriptor.size;
+    let (width, depth) = (size.width as usize, size.height as usize);
+    let stride = (image.data.len() / (width * depth).max(1)).max(1);
+    let pixels: Vec<u8> = image.data.iter().step_by(stride).copied().collect();
+    Heightmap::from_gray(width, depth, &pixels, max_height)
+}
+
+/// Allows optional fields to be written without `Some(...)` in the ron files.
+fn implicit_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
 #[derive(Debug, Default, Clone, Deserialize)]
@@ -21,25 +143,180 @@ pub struct TileMesh {
     pub scale: Vec3,
 }
 
+/// A mesh of a tile and how likely it is picked, relative to the other meshes of the tile.
+/// The `scene` of a tile has a weight of 1.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VisualVariant {
+    pub scene: TileMesh,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
 #[derive(Debug, Clone, Default, Deserialize, TypeUuid)]
 #[uuid = "3178ccfa-d18a-4a9f-be8b-b3233f77510b"]
 pub struct ProblemDescriptionAsset {
+    #[serde(default)]
+    mode: DescriptionMode,
+    /// Width, height and depth. A 2D description uses width and depth and ignores the height.
     dimensions: (usize, usize, usize),
     connections: Vec<String>,
-    tiles: Vec<String>,
+    tiles: Vec<TileSource>,
+    #[serde(default)]
+    profiles: HashMap<String, Vec<HorizontalConnection>>,
+    #[serde(default)]
+    templates: HashMap<String, TileDefinition>,
+    #[serde(default)]
+    counts: Vec<CountConstraint<String>>,
+    #[serde(default)]
+    connectivity: Vec<ConnectivityConstraint<String>>,
+    /// Layers of the generation volume from the bottom up, one string per row along z and one
+    /// character per cell along x, `.` marks a masked out cell. The last layer is repeated
+    /// up to the full height.
+    #[serde(default)]
+    mask: Vec<Vec<String>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    mask_tile: Option<String>,
+    /// Per cell factors on tile weights, e.g.
+    /// `(selector: Tag("building"), map: Gradient(Radial(centre: 4.0, edge: 0.5)))`.
+    /// On a plane the 2D `y` axis is `Z`.
+    #[serde(default)]
+    weights: Vec<WeightField<String>>,
+    /// Roads and rivers drawn as polylines or splines.
+    #[serde(default)]
+    paths: Vec<PathConstraint<String>>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    zones: Option<ZoneSource>,
+    #[serde(default)]
+    prefabs: Vec<PrefabSource>,
+    /// Target heights of the columns of a volume.
+    #[serde(default, deserialize_with = "implicit_some")]
+    heightmap: Option<HeightmapSource>,
+    /// Symmetries of the output, e.g. `[MirrorX]` for a symmetric facade.
+    #[serde(default)]
+    symmetry: Vec<Symmetry>,
+    /// Target shares of tile ids, e.g. `(targets: [("block", 0.6), ("window", 0.4)])`.
+    #[serde(default, deserialize_with = "implicit_some")]
+    distribution: Option<Distribution<String>>,
+    /// Path to a `.rules` file that is run over every solution.
+    #[serde(default, deserialize_with = "implicit_some")]
+    rules: Option<String>,
+}
+
+/// Regions of the grid that each use their own tiles, by zone symbol. The `layout` is written
+/// like the mask with the symbol of the zone of every cell.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneSource {
+    zones: HashMap<char, Zone<String>>,
+    layout: Vec<Vec<String>>,
+    /// Tiles that may also be placed where two zones meet.
+    #[serde(default)]
+    transitions: Vec<TileSelector<String>>,
+}
+
+/// A `.prefab` file to stamp into the grid. Position and rotation that are left out are
+/// chosen by the solver.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrefabSource {
+    path: String,
+    #[serde(default, deserialize_with = "implicit_some")]
+    at: Option<Point>,
+    #[serde(default, deserialize_with = "implicit_some")]
+    rotation: Option<TileRotation>,
+}
+
+/// A hand-authored block of tiles. The layers are written like the mask of a description,
+/// without repeating the last layer. Symbols in the `legend` place a tile in a rotation, any
+/// other symbol leaves the cell to the solver.
+#[derive(Debug, Clone, Default, Deserialize, TypeUuid)]
+#[uuid = "0c209089-f046-4c49-b570-edde51f5fe0f"]
+pub struct PrefabAsset {
+    pub layers: Vec<Vec<String>>,
+    pub legend: HashMap<char, (String, TileRotation)>,
+}
+
+impl PrefabAsset {
+    pub fn prefab(&self) -> Prefab<String> {
+        let width = self
+            .layers
+            .iter()
+            .flatten()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let depth = self.layers.iter().map(Vec::len).max().unwrap_or(0);
+        let dimensions = Dimensions::new(width, self.layers.len(), depth);
+
+        let mut prefab = Prefab::new(dimensions);
+        for point in dimensions.points() {
+            let tile = layout_cell(&self.layers, point).and_then(|cell| self.legend.get(&cell));
+            prefab.set(point, tile.cloned());
+        }
+        prefab
+    }
+}
+
+/// Rewrite rules that are run in order over a solved grid, e.g.
+/// `(tile: Tile("block"), neighbours: [(Backward, Tile("air"))], replace: "block_ivy")`.
+#[derive(Debug, Clone, Default, Deserialize, TypeUuid)]
+#[uuid = "6a5e1c0d-2f4b-4b8e-9d63-7e0f3a91c5b2"]
+pub struct RewriteRulesAsset {
+    pub rules: Vec<RewriteRule<String>>,
+}
+
+/// A grayscale image with one pixel per `(x, z)` column, white is the height of the grid.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeightmapSource {
+    path: String,
+    /// Tiles allowed below the height of a column.
+    below: Vec<TileSelector<String>>,
+    /// Tiles allowed at and above the height of a column.
+    above: Vec<TileSelector<String>>,
+}
+
+/// Whether tiles are generated in a volume, or on a plane where their up and down faces are
+/// ignored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum DescriptionMode {
+    #[default]
+    Volume,
+    Plane,
+}
+
+/// A tile of a description: either the path to a `.tile` file, or the tile written inline
+/// so that a whole tileset fits in a single file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TileSource {
+    Path(String),
+    Inline(Box<TileAsset>),
 }
 
 #[derive(Component)]
 struct LoadingProblemTag;
 
+/// Handles are aligned with the tiles of the description, `None` for inline tiles. They are
+/// followed by the heightmap image, if the description uses one, the prefabs and the rewrite
+/// rules.
 #[derive(Component)]
-struct LoadingTilesTag(ProblemDescriptionAsset, Vec<Handle<TileAsset>>);
+struct LoadingTilesTag(
+    ProblemDescriptionAsset,
+    Vec<Option<Handle<TileAsset>>>,
+    Option<Handle<Image>>,
+    Vec<Handle<PrefabAsset>>,
+    Option<Handle<RewriteRulesAsset>>,
+);
 pub struct WfcAssetPlugin;
 
 impl Plugin for WfcAssetPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_plugin(RonAssetPlugin::<TileAsset>::new(&["tile"]))
-            .add_plugin(RonAssetPlugin::<ProblemDescriptionAsset>::new(&["desc"]))
+            .add_plugin(RonAssetPlugin::<PrefabAsset>::new(&["prefab"]))
+            .add_plugin(RonAssetPlugin::<RewriteRulesAsset>::new(&["rules"]))
+            .add_plugin(RonAssetPlugin::<ProblemDescriptionAsset>::new(&["desc", "tileset"]))
             .add_startup_system(setup_description)
             .add_system(description_asset_loader)
             .add_system(tile_asset_loader.after(description_asset_loader));
@@ -64,13 +341,37 @@ fn description_asset_loader(
             let handles = description
                 .tiles
                 .iter()
-                .map(|x: &String| -> Handle<TileAsset> { asset_server.load(x) })
+                .map(|x: &TileSource| -> Option<Handle<TileAsset>> {
+                    match x {
+                        TileSource::Path(path) => Some(asset_server.load(path.as_str())),
+                        TileSource::Inline(_) => None,
+                    }
+                })
                 .collect::<Vec<_>>();
+            let heightmap = description
+                .heightmap
+                .as_ref()
+                .map(|source| asset_server.load(source.path.as_str()));
+            let prefabs = description
+                .prefabs
+                .iter()
+                .map(|source| asset_server.load(source.path.as_str()))
+                .collect();
+            let rules = description
+                .rules
+                .as_ref()
+                .map(|path| asset_server.load(path.as_str()));
 
             commands
                 .entity(entity)
                 .remove::<LoadingProblemTag>()
-                .insert(LoadingTilesTag(description.clone(), handles));
+                .insert(LoadingTilesTag(
+                    description.clone(),
+                    handles,
+                    heightmap,
+                    prefabs,
+                    rules,
+                ));
         }
     });
 }
@@ -78,45 +379,164 @@ fn description_asset_loader(
 fn tile_asset_loader(
     mut commands: Commands,
     res: Res<Assets<TileAsset>>,
+    images: Res<Assets<Image>>,
+    prefab_assets: Res<Assets<PrefabAsset>>,
+    rule_assets: Res<Assets<RewriteRulesAsset>>,
     query: Query<(Entity, &LoadingTilesTag)>,
     asset_server: Res<AssetServer>,
 ) {
-    query.for_each(|(entity, LoadingTilesTag(description, handles))| {
-        let handles = handles.iter().map(|x| res.get(x)).collect::<Vec<_>>();
+    query.for_each(|(entity, loading)| {
+        let LoadingTilesTag(description, handles, image, prefabs, rules) = loading;
+        let rules = match rules {
+            Some(handle) => match rule_assets.get(handle) {
+                Some(asset) => asset.rules.clone(),
+                None => return,
+            },
+            None => Vec::new(),
+        };
+        let prefabs = match prefabs
+            .iter()
+            .map(|handle| prefab_assets.get(handle))
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(prefabs) => description
+                .prefabs
+                .iter()
+                .zip(prefabs)
+                .map(|(source, asset)| {
+                    PrefabPlacement::new(asset.prefab(), source.at, source.rotation)
+                })
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+        let heightmap = match image {
+            Some(handle) => match images.get(handle) {
+                Some(image) => Some(image_heightmap(image, description.dimensions.1)),
+                None => return,
+            },
+            None => None,
+        };
+        let handles = description
+            .tiles
+            .iter()
+            .zip(handles)
+            .map(|(source, handle)| match (source, handle) {
+                (TileSource::Inline(tile), _) => Some(tile.as_ref()),
+                (_, Some(handle)) => res.get(handle),
+                (_, None) => None,
+            })
+            .collect::<Vec<_>>();
         if handles.iter().all(|x| x.is_some()) {
             let tiles = handles
                 .iter()
                 .map(|x| x.unwrap())
                 .collect::<Vec<&TileAsset>>();
 
-            let description = ProblemDescription {
-                connections: description.connections.clone(),
-                dimensions: Dimensions::new(
-                    description.dimensions.0,
-                    description.dimensions.1,
-                    description.dimensions.2,
-                ),
-                tiles: tiles
+            let resolved = match TemplateResolver::new(description, &tiles).and_then(|resolver| {
+                tiles
                     .iter()
-                    .map(|x| x.tile.clone())
-                    .collect::<Vec<Tile<String>>>(),
+                    .map(|x| resolver.resolve(&x.tile))
+                    .collect::<Result<Vec<Tile<String>>, TemplateError>>()
+            }) {
+                Ok(resolved) => resolved,
+                Err(err) => {
+                    error!("Failed resolving tile templates: {}", err);
+                    commands.entity(entity).despawn_recursive();
+                    return;
+                }
+            };
+
+            let height_guide = match (&description.heightmap, heightmap) {
+                (Some(source), Some(map)) => Some(HeightGuide::new(
+                    map,
+                    source.below.clone(),
+                    source.above.clone(),
+                )),
+                _ => None,
+            };
+            let description = match description.mode {
+                DescriptionMode::Volume => {
+                    let dimensions = Dimensions::new(
+                        description.dimensions.0,
+                        description.dimensions.1,
+                        description.dimensions.2,
+                    );
+                    ProblemDescription {
+                        connections: description.connections.clone(),
+                        dimensions,
+                        tiles: resolved,
+                        counts: description.counts.clone(),
+                        connectivity: description.connectivity.clone(),
+                        paths: description.paths.clone(),
+                        mask: parse_mask(&description.mask, dimensions),
+                        mask_tile: description.mask_tile.clone(),
+                        weight_fields: description.weights.clone(),
+                        height_guide,
+                        zones: description
+                            .zones
+                            .as_ref()
+                            .map(|zones| parse_zones(zones, dimensions)),
+                        prefabs,
+                        symmetry: description.symmetry.clone(),
+                        distribution: description.distribution.clone(),
+                        ..Default::default()
+                    }
+                }
+                DescriptionMode::Plane => ProblemDescription {
+                    distribution: description.distribution.clone(),
+                    weight_fields: description.weights.clone(),
+                    prefabs,
+                    zones: description.zones.as_ref().map(|zones| {
+                        let dimensions =
+                            Dimensions::new(description.dimensions.0, 1, description.dimensions.2);
+                        parse_zones(zones, dimensions)
+                    }),
+                    ..ProblemDescription2d {
+                        connections: description.connections.clone(),
+                        width: description.dimensions.0,
+                        height: description.dimensions.2,
+                        tiles: resolved.into_iter().map(plane_tile).collect(),
+                        counts: description.counts.clone(),
+                        connectivity: description.connectivity.clone(),
+                        paths: description.paths.clone(),
+                        symmetry: description.symmetry.clone(),
+                        ..Default::default()
+                    }
+                    .into_3d()
+                },
             };
 
             let mapper = tiles
                 .iter()
                 .map(|x: &&TileAsset| {
-                    (
-                        x.tile.id.clone(),
-                        x.scene
-                            .clone()
-                            .map(|scene: TileMesh| (scene.clone(), asset_server.load(&scene.path))),
-                    )
+                    let variants = x
+                        .scene
+                        .iter()
+                        .map(|scene| (scene, 1.0))
+                        .chain(x.variants.iter().map(|v| (&v.scene, v.weight)))
+                        .map(|(scene, weight)| SceneVariant {
+                            handle: asset_server.load(&scene.path),
+                            weight,
+                        })
+                        .collect();
+                    (x.tile.id.clone(), variants)
+                })
+                .collect();
+
+            let sprites = tiles
+                .iter()
+                .filter_map(|x: &&TileAsset| {
+                    x.sprite
+                        .as_ref()
+                        .map(|path| (x.tile.id.clone(), asset_server.load(path.as_str())))
                 })
                 .collect();
 
             let resource = WfcProblemResource {
                 description,
                 mapper,
+                sprites,
+                rules,
             };
             commands.insert_resource(resource);
 
@@ -128,5 +548,297 @@ fn tile_asset_loader(
 #[derive(Debug)]
 pub struct WfcProblemResource {
     pub description: ProblemDescription<String>,
-    pub mapper: HashMap<String, Option<(TileMesh, Handle<Scene>)>>,
+    pub mapper: HashMap<String, Vec<SceneVariant>>,
+    pub sprites: HashMap<String, Handle<Image>>,
+    pub rules: Vec<RewriteRule<String>>,
+}
+
+/// A loaded mesh of a tile.
+#[derive(Debug, Clone)]
+pub struct SceneVariant {
+    pub handle: Handle<Scene>,
+    pub weight: f32,
+}
+
+fn plane_tile(tile: Tile<String>) -> Tile2d<String> {
+    Tile2d {
+        id: tile.id,
+        left: tile.left,
+        right: tile.right,
+        forward: tile.forward,
+        backward: tile.backward,
+        can_rotate: tile.can_rotate,
+        can_flip: tile.can_flip,
+        weight: tile.weight,
+        metadata: tile.metadata,
+        tags: tile.tags,
+        placement: tile.placement,
+        decorative: tile.decorative,
+    }
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    UnknownTemplate(String),
+    UnknownProfile(String),
+    Cycle(String),
+    /// A template has the name of a tile, so `extends` could mean either.
+    NameCollision(String),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::UnknownTemplate(name) => write!(f, "Unknown tile template: {}", name),
+            TemplateError::UnknownProfile(name) => write!(f, "Unknown socket profile: {}", name),
+            TemplateError::Cycle(name) => write!(f, "Tile template {} extends itself", name),
+            TemplateError::NameCollision(name) => {
+                write!(f, "Tile template {} has the name of a tile", name)
+            }
+        }
+    }
+}
+
+/// Flattens `extends` chains and expands socket profiles into plain solver tiles.
+/// A tile can extend a template of the description, or another tile of the same set by id.
+struct TemplateResolver<'a> {
+    profiles: &'a HashMap<String, Vec<HorizontalConnection>>,
+    templates: HashMap<&'a str, &'a TileDefinition>,
+}
+
+impl<'a> TemplateResolver<'a> {
+    /// Template names and tile ids share the names `extends` refers to, so they may not
+    /// overlap.
+    fn new(
+        description: &'a ProblemDescriptionAsset,
+        tiles: &[&'a TileAsset],
+    ) -> Result<Self, TemplateError> {
+        let mut templates = HashMap::new();
+        for tile in tiles {
+            templates.insert(tile.tile.id.as_str(), &tile.tile);
+        }
+        for (name, template) in &description.templates {
+            if templates.insert(name.as_str(), template).is_some() {
+                return Err(TemplateError::NameCollision(name.clone()));
+            }
+        }
+
+        Ok(Self {
+            profiles: &description.profiles,
+            templates,
+        })
+    }
+
+    fn resolve(&self, definition: &TileDefinition) -> Result<Tile<String>, TemplateError> {
+        let flat = self.flatten(definition, &mut Vec::new())?;
+
+        Ok(Tile {
+            id: definition.id.clone(),
+            up: self.vertical(&flat.up)?,
+            down: self.vertical(&flat.down)?,
+            left: self.horizontal(&flat.left)?,
+            right: self.horizontal(&flat.right)?,
+            forward: self.horizontal(&flat.forward)?,
+            backward: self.horizontal(&flat.backward)?,
+            can_rotate: flat.can_rotate.unwrap_or_default(),
+            can_flip: flat.can_flip.unwrap_or_default(),
+            weight: flat.weight.unwrap_or(1.0),
+            metadata: flat.metadata,
+            tags: flat.tags,
+            placement: flat.placement.unwrap_or_default(),
+            footprint: flat.footprint.unwrap_or((1, 1, 1)),
+            decorative: flat.decorative.unwrap_or_default(),
+        })
+    }
+
+    fn flatten(
+        &self,
+        definition: &TileDefinition,
+        visiting: &mut Vec<String>,
+    ) -> Result<TileDefinition, TemplateError> {
+        let name = match &definition.extends {
+            Some(name) => name,
+            None => return Ok(definition.clone()),
+        };
+
+        if visiting.contains(name) {
+            return Err(TemplateError::Cycle(name.clone()));
+        }
+
+        let base = self
+            .templates
+            .get(name.as_str())
+            .ok_or_else(|| TemplateError::UnknownTemplate(name.clone()))?;
+
+        visiting.push(name.clone());
+        let base = self.flatten(base, visiting)?;
+        visiting.pop();
+
+        Ok(definition.inherit(base))
+    }
+
+    fn profile(&self, name: &str) -> Result<&Vec<HorizontalConnection>, TemplateError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| TemplateError::UnknownProfile(name.to_string()))
+    }
+
+    fn horizontal(
+        &self,
+        face: &Option<Vec<SocketEntry<HorizontalConnection>>>,
+    ) -> Result<Vec<HorizontalConnection>, TemplateError> {
+        let mut sockets = Vec::new();
+        for entry in face.iter().flatten() {
+            match entry {
+                SocketEntry::Profile(name) => sockets.extend(self.profile(name)?.iter().cloned()),
+                SocketEntry::Socket(socket) => sockets.push(socket.clone()),
+            }
+        }
+        Ok(sockets)
+    }
+
+    fn vertical(
+        &self,
+        face: &Option<Vec<SocketEntry<VerticalConnection>>>,
+    ) -> Result<Vec<VerticalConnection>, TemplateError> {
+        let mut sockets = Vec::new();
+        for entry in face.iter().flatten() {
+            match entry {
+                SocketEntry::Profile(name) => {
+                    sockets.extend(self.profile(name)?.iter().map(|socket| VerticalConnection {
+                        connection: socket.connection.clone(),
+                    }))
+                }
+                SocketEntry::Socket(socket) => sockets.push(socket.clone()),
+            }
+        }
+        Ok(sockets)
+    }
+}
+
+impl TileDefinition {
+    /// Fills in everything this definition leaves out from `base`.
+    fn inherit(&self, base: TileDefinition) -> TileDefinition {
+        let mut metadata = base.metadata;
+        metadata.extend(self.metadata.clone());
+
+        let mut tags = base.tags;
+        for tag in &self.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        TileDefinition {
+            id: self.id.clone(),
+            extends: None,
+            up: self.up.clone().or(base.up),
+            down: self.down.clone().or(base.down),
+            left: self.left.clone().or(base.left),
+            right: self.right.clone().or(base.right),
+            forward: self.forward.clone().or(base.forward),
+            backward: self.backward.clone().or(base.backward),
+            can_rotate: self.can_rotate.or(base.can_rotate),
+            can_flip: self.can_flip.or(base.can_flip),
+            weight: self.weight.or(base.weight),
+            metadata,
+            tags,
+            placement: self.placement.clone().or(base.placement),
+            footprint: self.footprint.or(base.footprint),
+            decorative: self.decorative.or(base.decorative),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(id: &str, extends: Option<&str>) -> TileDefinition {
+        TileDefinition {
+            id: id.to_string(),
+            extends: extends.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn tile(id: &str, extends: Option<&str>) -> TileAsset {
+        TileAsset {
+            tile: definition(id, extends),
+            ..Default::default()
+        }
+    }
+
+    fn description(templates: Vec<TileDefinition>) -> ProblemDescriptionAsset {
+        ProblemDescriptionAsset {
+            templates: templates
+                .into_iter()
+                .map(|template| (template.id.clone(), template))
+                .collect(),
+            profiles: [(
+                "wall".to_string(),
+                vec![HorizontalConnection {
+                    connection: "wall".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_template() {
+        let mut base = definition("base", None);
+        base.left = Some(vec![SocketEntry::Profile("wall".into())]);
+        base.weight = Some(2.0);
+        let description = description(vec![base]);
+        let block = tile("block", Some("base"));
+        let tiles = [&block];
+
+        let resolver = TemplateResolver::new(&description, &tiles).unwrap();
+        let resolved = resolver.resolve(&block.tile).unwrap();
+        assert_eq!("block", resolved.id);
+        assert_eq!(2.0, resolved.weight);
+        assert_eq!("wall", resolved.left[0].connection);
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let description = description(vec![definition("a", Some("b")), definition("b", Some("a"))]);
+        let cyclic = tile("block", Some("a"));
+        let missing = tile("window", Some("frame"));
+        let tiles = [&cyclic, &missing];
+        let resolver = TemplateResolver::new(&description, &tiles).unwrap();
+
+        assert!(matches!(
+            resolver.resolve(&cyclic.tile),
+            Err(TemplateError::Cycle(_))
+        ));
+        assert!(matches!(
+            resolver.resolve(&missing.tile),
+            Err(TemplateError::UnknownTemplate(name)) if name == "frame"
+        ));
+
+        let mut profile = definition("block", None);
+        profile.up = Some(vec![SocketEntry::Profile("roof".into())]);
+        assert!(matches!(
+            resolver.resolve(&profile),
+            Err(TemplateError::UnknownProfile(name)) if name == "roof"
+        ));
+    }
+
+    #[test]
+    fn test_template_name_collision() {
+        let description = description(vec![definition("block", None)]);
+        let block = tile("block", None);
+        let tiles = [&block];
+
+        assert!(matches!(
+            TemplateResolver::new(&description, &tiles),
+            Err(TemplateError::NameCollision(name)) if name == "block"
+        ));
+    }
 }
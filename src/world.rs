@@ -1,7 +1,7 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::EntityCommands, prelude::*};
 use wfc_solver::{
-    description::{TileData, TileRotation},
-    utils::Point,
+    description::{TileData, TileMetadata, TileRotation},
+    utils::{Dimensions, Point},
 };
 
 use crate::{ui::LoadedSolution, wfc_asset::WfcProblemResource};
@@ -9,6 +9,15 @@ use crate::{ui::LoadedSolution, wfc_asset::WfcProblemResource};
 #[derive(Debug, Component)]
 pub struct WorldTag;
 
+/// Gameplay metadata of the tile that was placed at this entity, with the annotations of
+/// the rewrite rules.
+#[derive(Debug, Component)]
+pub struct TileMetadataTag(pub TileMetadata);
+
+/// The camera that draws the sprites of 2D descriptions.
+#[derive(Debug, Component)]
+pub struct SpriteCameraTag;
+
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
@@ -21,6 +30,7 @@ fn load_solution(
     mut commands: Commands,
     wfc: Option<Res<WfcProblemResource>>,
     old_world: Query<Entity, With<WorldTag>>,
+    sprite_camera: Query<(), With<SpriteCameraTag>>,
     query: Query<(Entity, &LoadedSolution)>,
 ) {
     let wfc = match wfc {
@@ -28,7 +38,13 @@ fn load_solution(
         None => return,
     };
 
-    if let Ok((entity, LoadedSolution(solution))) = query.get_single() {
+    if !wfc.sprites.is_empty() && sprite_camera.is_empty() {
+        commands
+            .spawn_bundle(OrthographicCameraBundle::new_2d())
+            .insert(SpriteCameraTag);
+    }
+
+    if let Ok((entity, LoadedSolution(solution, seed))) = query.get_single() {
         if let Ok(entity) = old_world.get_single() {
             println!("Despawning old world");
             commands.entity(entity).despawn_recursive();
@@ -40,61 +56,157 @@ fn load_solution(
             .insert(WorldTag)
             .insert_bundle(TransformBundle::default())
             .with_children(|cb| {
-                let dimensions = solution.grid.dimensions();
                 println!("Spawning children");
-                for x in 0..dimensions.width() {
-                    for y in 0..dimensions.height() {
-                        for z in 0..dimensions.depth() {
-                            let tile = solution
-                                .grid
-                                .get(Point::new(x, y, z))
-                                .unwrap()
-                                .iter_ones()
-                                .next()
-                                .unwrap();
-
-                            let data = solution.compiled.data(tile);
-
-                            if let Some(scene) = get_scene_handle(data, &wfc) {
-                                let mut transform = Transform::from_translation(Vec3::new(
-                                    x as f32 * 1.0,
-                                    y as f32 * 1.0,
-                                    z as f32 * 1.0,
-                                ));
-                                println!("Data: {:?} - {:?}", data, transform);
-
-                                if data.flipped {
-                                    transform = transform.with_scale(Vec3::new(-1.0, 1.0, 1.0));
-                                }
-
-                                let rotation = match data.rotation {
-                                    TileRotation::R0 => 0f32,
-                                    TileRotation::R90 => 90f32,
-                                    TileRotation::R180 => 180f32,
-                                    TileRotation::R270 => 270f32,
-                                }
-                                .to_radians();
-
-                                transform.rotate(Quat::from_rotation_y(rotation));
-
-                                cb.spawn_bundle(TransformBundle {
-                                    local: transform,
-                                    ..Default::default()
-                                })
-                                .with_children(|cb| {
-                                    cb.spawn_scene(scene);
-                                });
-                            }
-                        }
+                // Masked out cells have no tile and multi-cell tiles spawn once, at their
+                // anchor.
+                for (point, data) in solution.placements() {
+                    let metadata = solution.cell_metadata(point).unwrap_or_default();
+                    let scene = get_scene_handle(data, &wfc, *seed, point);
+
+                    if let Some(texture) = wfc.sprites.get(&tile_id(data, &wfc)) {
+                        spawn_sprite(cb, texture.clone(), point, data)
+                            .insert(TileMetadataTag(metadata));
+                        continue;
                     }
+
+                    if scene.is_none() && metadata.is_empty() {
+                        continue;
+                    }
+
+                    // A multi-cell tile turns about the centre of its footprint, so its mesh
+                    // stays inside the cells the solver reserved for it.
+                    let tile = &wfc.description.tiles[data.tile_index];
+                    let footprint = tile.rotated_footprint(TileRotation::R0);
+                    let mut transform = Transform::from_translation(
+                        Vec3::new(
+                            point.x() as f32 * 1.0,
+                            point.y() as f32 * 1.0,
+                            point.z() as f32 * 1.0,
+                        ) + footprint_centre(tile.rotated_footprint(data.rotation)),
+                    );
+                    println!("Data: {:?} - {:?}", data, transform);
+
+                    if data.flipped {
+                        transform = transform.with_scale(Vec3::new(-1.0, 1.0, 1.0));
+                    }
+
+                    let rotation = match data.rotation {
+                        TileRotation::R0 => 0f32,
+                        TileRotation::R90 => 90f32,
+                        TileRotation::R180 => 180f32,
+                        TileRotation::R270 => 270f32,
+                    }
+                    .to_radians();
+
+                    transform.rotate(Quat::from_rotation_y(rotation));
+
+                    cb.spawn_bundle(TransformBundle {
+                        local: transform,
+                        ..Default::default()
+                    })
+                    .insert(TileMetadataTag(metadata))
+                    .with_children(|cb| {
+                        if let Some(scene) = scene {
+                            cb.spawn_bundle(TransformBundle {
+                                local: Transform::from_translation(-footprint_centre(footprint)),
+                                ..Default::default()
+                            })
+                            .with_children(|cb| {
+                                cb.spawn_scene(scene);
+                            });
+                        }
+                    });
                 }
             });
     }
 }
 
-fn get_scene_handle(data: &TileData, wfc: &WfcProblemResource) -> Option<Handle<Scene>> {
-    let tile = &wfc.description.tiles[data.tile_index];
-    wfc.mapper[&tile.id]
-        .as_ref()
-        .map(|(_, handle)| handle.clone())
+/// Offset from the anchor cell to the centre of a footprint of the given size.
+fn footprint_centre(footprint: Dimensions) -> Vec3 {
+    Vec3::new(
+        footprint.width() as f32 - 1.0,
+        footprint.height() as f32 - 1.0,
+        footprint.depth() as f32 - 1.0,
+    ) / 2.0
+}
+
+/// Size of a sprite cell in a 2D description.
+const SPRITE_SIZE: f32 = 32.0;
+
+/// Lays out the plane of a 2D description on screen, the layer decides the drawing order.
+fn spawn_sprite<'w, 's, 'a>(
+    cb: &'a mut ChildBuilder<'w, 's, '_>,
+    texture: Handle<Image>,
+    point: Point,
+    data: &TileData,
+) -> EntityCommands<'w, 's, 'a> {
+    let rotation = match data.rotation {
+        TileRotation::R0 => 0f32,
+        TileRotation::R90 => 90f32,
+        TileRotation::R180 => 180f32,
+        TileRotation::R270 => 270f32,
+    }
+    .to_radians();
+
+    let mut transform = Transform::from_translation(Vec3::new(
+        point.x() as f32 * SPRITE_SIZE,
+        point.z() as f32 * SPRITE_SIZE,
+        point.y() as f32,
+    ));
+    if data.flipped {
+        transform = transform.with_scale(Vec3::new(-1.0, 1.0, 1.0));
+    }
+    // Seen from above, turning around the y axis of the volume is clockwise on screen.
+    transform.rotate(Quat::from_rotation_z(-rotation));
+
+    cb.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(Vec2::splat(SPRITE_SIZE)),
+            ..Default::default()
+        },
+        texture,
+        transform,
+        ..Default::default()
+    })
+}
+
+fn tile_id(data: &TileData, wfc: &WfcProblemResource) -> String {
+    wfc.description.tiles[data.tile_index].id.clone()
+}
+
+/// Picks one of the meshes of the tile. The choice only depends on the seed and the cell, so
+/// the same solution always looks the same.
+fn get_scene_handle(
+    data: &TileData,
+    wfc: &WfcProblemResource,
+    seed: u64,
+    point: Point,
+) -> Option<Handle<Scene>> {
+    let variants = &wfc.mapper[&tile_id(data, wfc)];
+    let total: f32 = variants.iter().map(|v| v.weight).sum();
+    if total <= 0.0 {
+        return variants.first().map(|v| v.handle.clone());
+    }
+
+    let hash = [point.x(), point.y(), point.z()]
+        .into_iter()
+        .fold(mix(seed), |hash, c| mix(hash ^ c as u64));
+    let mut pick = (hash as f64 / u64::MAX as f64) as f32 * total;
+    variants
+        .iter()
+        .find(|v| {
+            pick -= v.weight;
+            pick < 0.0
+        })
+        .or_else(|| variants.last())
+        .map(|v| v.handle.clone())
+}
+
+/// The splitmix64 finaliser. Unlike the std hashers its output is fixed, so a saved seed
+/// keeps its look across Rust releases.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
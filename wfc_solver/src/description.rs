@@ -1,9 +1,17 @@
-use crate::utils::{Dimensions, Point};
+use crate::{
+    error::ProblemError,
+    topology::Adjacency,
+    utils::{Dimensions, Direction, FieldGrid, Heightmap, Mask, Point, WeightGrid, ZoneLayout},
+};
 use bitvec::prelude::BitVec;
 use serde::Deserialize;
-use std::ops::Neg;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    ops::{Add, Neg},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum TileRotation {
     R0,
     R90,
@@ -24,7 +32,23 @@ impl Neg for TileRotation {
     }
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+impl Add for TileRotation {
+    type Output = Self;
+
+    /// Rotates by `rhs` on top of `self`.
+    fn add(self, rhs: Self) -> Self::Output {
+        let all = [
+            TileRotation::R0,
+            TileRotation::R90,
+            TileRotation::R180,
+            TileRotation::R270,
+        ];
+        let index = |r| all.iter().position(|x| *x == r).unwrap();
+        all[(index(self) + index(rhs)) % 4]
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct Tile<D> {
     pub id: D,
     pub up: Vec<VerticalConnection>,
@@ -36,9 +60,116 @@ pub struct Tile<D> {
 
     pub can_rotate: bool,
     pub can_flip: bool,
+
+    /// Relative chance of picking this tile when a cell is collapsed. Every cell of the
+    /// footprint of a multi-cell tile is a variant with this weight, so a cell picks a bigger
+    /// tile more often than its weight alone suggests.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+
+    #[serde(default)]
+    pub metadata: TileMetadata,
+
+    /// Free-form labels that constraints can select tiles by.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Where in the grid the tile may be placed.
+    #[serde(default)]
+    pub placement: Placement,
+
+    /// Cells the tile covers along x, y and z, starting at the cell it is anchored in.
+    /// Every face of the footprint uses the sockets of the matching side of the tile.
+    #[serde(default = "default_footprint")]
+    pub footprint: (usize, usize, usize),
+
+    /// Only placed by rewrite rules. The solver never picks the tile and never prunes its
+    /// variants, so every rotation of it stays available as a replacement.
+    #[serde(default)]
+    pub decorative: bool,
+}
+
+fn default_footprint() -> (usize, usize, usize) {
+    (1, 1, 1)
+}
+
+/// Positional rules for a tile. The border is the outer ring of the grid, the cells that
+/// touch its left, right, forward or backward side.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Placement {
+    #[serde(default)]
+    pub min_y: Option<usize>,
+    #[serde(default)]
+    pub max_y: Option<usize>,
+    #[serde(default)]
+    pub border: Option<BorderRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum BorderRule {
+    /// Only on the outer ring.
+    Only,
+    /// Never touching the border.
+    Never,
+}
+
+impl Placement {
+    pub fn allows(&self, point: Point, dimensions: Dimensions) -> bool {
+        if matches!(self.min_y, Some(min) if point.y() < min)
+            || matches!(self.max_y, Some(max) if point.y() > max)
+        {
+            return false;
+        }
+
+        let on_border = point.x() == 0
+            || point.z() == 0
+            || point.x() + 1 == dimensions.width()
+            || point.z() + 1 == dimensions.depth();
+
+        match self.border {
+            Some(BorderRule::Only) => on_border,
+            Some(BorderRule::Never) => !on_border,
+            None => true,
+        }
+    }
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl<D: Default> Default for Tile<D> {
+    fn default() -> Self {
+        Self {
+            id: D::default(),
+            up: Vec::new(),
+            down: Vec::new(),
+            left: Vec::new(),
+            right: Vec::new(),
+            forward: Vec::new(),
+            backward: Vec::new(),
+            can_rotate: false,
+            can_flip: false,
+            weight: default_weight(),
+            metadata: TileMetadata::default(),
+            tags: Vec::new(),
+            placement: Placement::default(),
+            footprint: default_footprint(),
+            decorative: false,
+        }
+    }
 }
 
 impl<D> Tile<D> {
+    /// The footprint once the tile is rotated, as dimensions.
+    pub fn rotated_footprint(&self, rotation: TileRotation) -> Dimensions {
+        let (width, height, depth) = self.footprint;
+        match rotation {
+            TileRotation::R0 | TileRotation::R180 => Dimensions::new(width, height, depth),
+            TileRotation::R90 | TileRotation::R270 => Dimensions::new(depth, height, width),
+        }
+    }
+
     pub fn get_horizontal_connection(
         &self,
         want_direction: TileRotation,
@@ -66,20 +197,702 @@ impl<D> Tile<D> {
             (TileRotation::R270, TileRotation::R270) => &self.forward,
         }
     }
+
+    /// The horizontal face that points in `direction` once the tile is rotated.
+    pub fn rotated_face(
+        &self,
+        direction: Direction,
+        rotation: TileRotation,
+    ) -> Option<&Vec<HorizontalConnection>> {
+        let want = match direction {
+            Direction::Up | Direction::Down => return None,
+            Direction::Forward => TileRotation::R0,
+            Direction::Right => TileRotation::R90,
+            Direction::Backward => TileRotation::R180,
+            Direction::Left => TileRotation::R270,
+        };
+        Some(self.get_horizontal_connection(want, rotation))
+    }
+
+    /// Socket names on the face that points in `direction` once the tile is rotated.
+    pub fn rotated_connections(&self, direction: Direction, rotation: TileRotation) -> Vec<&str> {
+        match self.rotated_face(direction, rotation) {
+            Some(face) => face.iter().map(|c| c.connection.as_str()).collect(),
+            None => self.connections(direction),
+        }
+    }
+
+    /// Socket names on the face of the unrotated tile.
+    pub fn connections(&self, direction: Direction) -> Vec<&str> {
+        match direction {
+            Direction::Up => self.up.iter().map(|c| c.connection.as_str()).collect(),
+            Direction::Down => self.down.iter().map(|c| c.connection.as_str()).collect(),
+            Direction::Left => self.left.iter().map(|c| c.connection.as_str()).collect(),
+            Direction::Right => self.right.iter().map(|c| c.connection.as_str()).collect(),
+            Direction::Forward => self.forward.iter().map(|c| c.connection.as_str()).collect(),
+            Direction::Backward => self.backward.iter().map(|c| c.connection.as_str()).collect(),
+        }
+    }
+}
+
+/// Selects the tiles a constraint applies to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum TileSelector<D> {
+    Tile(D),
+    Tag(String),
+    /// Tiles that have the socket on any of their faces.
+    Socket(String),
+}
+
+impl<D: PartialEq> TileSelector<D> {
+    pub fn matches(&self, tile: &Tile<D>) -> bool {
+        match self {
+            TileSelector::Tile(id) => tile.id == *id,
+            TileSelector::Tag(tag) => tile.tags.contains(tag),
+            TileSelector::Socket(socket) => Direction::ALL
+                .iter()
+                .any(|d| tile.connections(*d).contains(&socket.as_str())),
+        }
+    }
+}
+
+/// A number of cells, either absolute or as a fraction of all cells in the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Amount {
+    Cells(usize),
+    Fraction(f32),
+}
+
+/// Limits how many cells in the whole grid may hold a tile matched by `selector`. A
+/// multi-cell tile counts once, in the cell it is anchored in.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CountConstraint<D> {
+    pub selector: TileSelector<D>,
+    #[serde(default)]
+    pub min: Option<Amount>,
+    #[serde(default)]
+    pub max: Option<Amount>,
+}
+
+impl<D> CountConstraint<D> {
+    pub fn new(selector: TileSelector<D>, min: Option<Amount>, max: Option<Amount>) -> Self {
+        Self { selector, min, max }
+    }
+}
+
+/// Target shares of tile ids among the active cells of the output. The solver steers its
+/// picks towards the targets and keeps every share within `tolerance` of its target. The
+/// bounds act as count constraints after those of the description, a share that can no
+/// longer be met is reported as one of them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Distribution<D> {
+    pub targets: Vec<(D, f32)>,
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+}
+
+fn default_tolerance() -> f32 {
+    0.1
+}
+
+impl<D> Distribution<D> {
+    pub fn new(targets: Vec<(D, f32)>, tolerance: f32) -> Self {
+        Self { targets, tolerance }
+    }
+}
+
+/// A distribution resolved to variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledDistribution {
+    /// The variants of each target tile, with its share.
+    pub targets: Vec<(BitVec, f32)>,
+    /// The count constraint that bounds the first target, the others follow it.
+    pub bounds: usize,
+    /// The number of active cells.
+    pub cells: usize,
+}
+
+impl CompiledDistribution {
+    /// Per variant, the factor on its weight that steers the shares towards their targets. A
+    /// tile that is behind its target gets more likely, one that is ahead gets less likely.
+    /// `open` is the number of cells that are not collapsed yet, the placed tiles are read from
+    /// the tracked variants of the bounds.
+    pub fn factors(&self, grid: &FieldGrid, open: usize) -> Vec<f32> {
+        let len = self
+            .targets
+            .first()
+            .map_or(0, |(variants, _)| variants.len());
+        let mut factors = vec![1.0; len];
+
+        for (index, (variants, share)) in self.targets.iter().enumerate() {
+            let assigned = grid.tracked(self.bounds + index).definite.count_ones() as f32;
+            let expected = share * open as f32;
+            if expected <= 0.0 {
+                continue;
+            }
+            let needed = (share * self.cells as f32 - assigned).max(0.0);
+            for variant in variants.iter_ones() {
+                factors[variant] *= needed / expected;
+            }
+        }
+        factors
+    }
+}
+
+/// All cells holding a tile matched by `selector` have to form a single region under the
+/// six-neighbour relation. The `anchors` have to be part of that region, so two anchors
+/// state that a path has to exist between them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConnectivityConstraint<D> {
+    pub selector: TileSelector<D>,
+    #[serde(default)]
+    pub anchors: Vec<Point>,
+}
+
+impl<D> ConnectivityConstraint<D> {
+    pub fn new(selector: TileSelector<D>, anchors: Vec<Point>) -> Self {
+        Self { selector, anchors }
+    }
+}
+
+/// Splits every column of the grid at the height of the heightmap. Cells below it may only
+/// hold tiles matched by one of `below`, cells at and above it only tiles matched by one of
+/// `above`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightGuide<D> {
+    pub heightmap: Heightmap,
+    pub below: Vec<TileSelector<D>>,
+    pub above: Vec<TileSelector<D>>,
+}
+
+impl<D> HeightGuide<D> {
+    pub fn new(
+        heightmap: Heightmap,
+        below: Vec<TileSelector<D>>,
+        above: Vec<TileSelector<D>>,
+    ) -> Self {
+        Self {
+            heightmap,
+            below,
+            above,
+        }
+    }
+}
+
+/// A height guide resolved to variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledHeightGuide {
+    pub heightmap: Heightmap,
+    pub below: BitVec,
+    pub above: BitVec,
+}
+
+/// A hand-authored block of tiles. Cells without a tile are left to the solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefab<D> {
+    dimensions: Dimensions,
+    cells: Vec<Option<(D, TileRotation)>>,
+}
+
+impl<D> Prefab<D> {
+    /// A prefab without any tiles.
+    pub fn new(dimensions: Dimensions) -> Self {
+        Self {
+            dimensions,
+            cells: (0..dimensions.len()).map(|_| None).collect(),
+        }
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn set(&mut self, point: Point, tile: Option<(D, TileRotation)>) {
+        self.cells[point.id(self.dimensions)] = tile;
+    }
+
+    pub fn get(&self, point: Point) -> Option<&(D, TileRotation)> {
+        self.cells[point.id(self.dimensions)].as_ref()
+    }
+
+    /// The dimensions and tiles of the prefab turned by `rotation` about the vertical axis.
+    /// Every quarter turn moves the forward face of the prefab to the right.
+    pub fn rotated(&self, rotation: TileRotation) -> (Dimensions, Vec<(Point, &D, TileRotation)>) {
+        let mut dimensions = self.dimensions;
+        let mut cells = self
+            .dimensions
+            .points()
+            .filter_map(|point| Some((point, self.get(point)?)))
+            .map(|(point, (id, rotation))| (point, id, *rotation))
+            .collect::<Vec<_>>();
+
+        let mut turn = TileRotation::R0;
+        while turn != rotation {
+            for (point, _, rotation) in &mut cells {
+                *point = Point::new(point.z(), point.y(), dimensions.width() - 1 - point.x());
+                *rotation = *rotation + TileRotation::R90;
+            }
+            dimensions =
+                Dimensions::new(dimensions.depth(), dimensions.height(), dimensions.width());
+            turn = turn + TileRotation::R90;
+        }
+        (dimensions, cells)
+    }
+}
+
+/// Stamps a prefab into the grid. The solver picks the position and the rotation that are
+/// not given, among those where the prefab fits, before solving the rest around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefabPlacement<D> {
+    pub prefab: Prefab<D>,
+    /// The cell the lowest corner of the prefab lies in.
+    pub at: Option<Point>,
+    pub rotation: Option<TileRotation>,
+}
+
+impl<D> PrefabPlacement<D> {
+    pub fn new(prefab: Prefab<D>, at: Option<Point>, rotation: Option<TileRotation>) -> Self {
+        Self {
+            prefab,
+            at,
+            rotation,
+        }
+    }
+
+    /// The rotations the prefab may be stamped with.
+    pub fn rotations(&self) -> Vec<TileRotation> {
+        match self.rotation {
+            Some(rotation) => vec![rotation],
+            None => vec![
+                TileRotation::R0,
+                TileRotation::R90,
+                TileRotation::R180,
+                TileRotation::R270,
+            ],
+        }
+    }
+}
+
+/// A prefab resolved to variants, per rotation the solver may use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledPrefab {
+    pub at: Option<Point>,
+    /// The dimensions of the rotated prefab and the domains of its cells, by offset.
+    pub rotations: Vec<(Dimensions, Vec<(Point, BitVec)>)>,
+}
+
+/// A symmetry the output has to keep: every cell holds the image of the tile in its image
+/// cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Symmetry {
+    /// Mirrored across the middle of the x axis.
+    MirrorX,
+    /// Mirrored across the middle of the z axis.
+    MirrorZ,
+    /// A half turn around the vertical centre line.
+    Rotate180,
+    /// A quarter turn around the vertical centre line, needs a square grid.
+    Rotate90,
+}
+
+impl Symmetry {
+    /// The cell `point` turns into inside a box of `dimensions`.
+    pub fn image(&self, point: Point, dimensions: Dimensions) -> Point {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let (width, depth) = (dimensions.width(), dimensions.depth());
+        match self {
+            Symmetry::MirrorX => Point::new(width - 1 - x, y, z),
+            Symmetry::MirrorZ => Point::new(x, y, depth - 1 - z),
+            Symmetry::Rotate180 => Point::new(width - 1 - x, y, depth - 1 - z),
+            Symmetry::Rotate90 => Point::new(z, y, width - 1 - x),
+        }
+    }
+
+    /// The cell whose image is `point`.
+    pub fn preimage(&self, point: Point, dimensions: Dimensions) -> Point {
+        match self {
+            Symmetry::Rotate90 => {
+                let (x, y, z) = (point.x(), point.y(), point.z());
+                Point::new(dimensions.width() - 1 - z, y, x)
+            }
+            _ => self.image(point, dimensions),
+        }
+    }
+
+    /// The rotations the image of a tile in `rotation` may have, the most likely first. A
+    /// mirror keeps the tile only when one of them shows its mirrored sockets.
+    fn rotations(&self, rotation: TileRotation) -> Vec<TileRotation> {
+        let all = [
+            TileRotation::R0,
+            TileRotation::R90,
+            TileRotation::R180,
+            TileRotation::R270,
+        ];
+        let inverse = all
+            .into_iter()
+            .find(|r| *r + rotation == TileRotation::R0)
+            .unwrap();
+        let first = match self {
+            Symmetry::MirrorX => inverse,
+            Symmetry::MirrorZ => -inverse,
+            Symmetry::Rotate180 => return vec![-rotation],
+            Symmetry::Rotate90 => return vec![rotation + TileRotation::R90],
+        };
+        let mut rotations = vec![first];
+        rotations.extend(all.into_iter().filter(|r| *r != first));
+        rotations
+    }
+
+    /// The side a face pointing in `direction` ends up on.
+    fn side(&self, direction: Direction) -> Direction {
+        match (self, direction) {
+            (Symmetry::MirrorX, Direction::Left | Direction::Right)
+            | (Symmetry::MirrorZ, Direction::Forward | Direction::Backward) => direction.opposite(),
+            _ => direction,
+        }
+    }
+
+    fn is_mirror(&self) -> bool {
+        matches!(self, Symmetry::MirrorX | Symmetry::MirrorZ)
+    }
+}
+
+/// A symmetry resolved to variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledSymmetry {
+    pub symmetry: Symmetry,
+    /// Per variant, the variant of its image, `None` when the tile has no variant that looks
+    /// like its image.
+    pub images: Vec<Option<usize>>,
+}
+
+/// The cells a path runs through.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum PathShape {
+    /// Straight segments between the points.
+    Polyline(Vec<Point>),
+    /// A Catmull-Rom spline through the points.
+    Spline(Vec<Point>),
+}
+
+impl PathShape {
+    /// The cells of the path in order, consecutive cells share a face.
+    pub fn cells(&self) -> Vec<Point> {
+        let points = match self {
+            PathShape::Polyline(points) => points.clone(),
+            PathShape::Spline(points) => spline_points(points),
+        };
+
+        let mut cells: Vec<Point> = points.first().copied().into_iter().collect();
+        for segment in points.windows(2) {
+            cells.extend(line_cells(segment[0], segment[1]).into_iter().skip(1));
+        }
+        cells
+    }
+}
+
+/// The cells from `from` to `to`, stepping along one axis at a time so that consecutive
+/// cells share a face. The axis that is furthest behind is stepped first.
+fn line_cells(from: Point, to: Point) -> Vec<Point> {
+    let start = [from.x(), from.y(), from.z()].map(|c| c as isize);
+    let end = [to.x(), to.y(), to.z()].map(|c| c as isize);
+    let delta = [0, 1, 2].map(|axis| end[axis] - start[axis]);
+    let mut done = [0isize; 3];
+
+    let mut cells = vec![from];
+    while (0..3).any(|axis| done[axis] < delta[axis].abs()) {
+        let axis = (0..3)
+            .filter(|axis| done[*axis] < delta[*axis].abs())
+            .min_by(|a, b| {
+                let progress = |axis: usize| done[axis] as f32 / delta[axis].abs() as f32;
+                progress(*a).total_cmp(&progress(*b))
+            })
+            .unwrap();
+        done[axis] += 1;
+
+        let at = |axis: usize| (start[axis] + done[axis] * delta[axis].signum()) as usize;
+        cells.push(Point::new(at(0), at(1), at(2)));
+    }
+    cells
+}
+
+/// Samples a Catmull-Rom spline through `points`, rounded to cells. The spline can overshoot
+/// its points, so the samples are clamped to their bounding box.
+fn spline_points(points: &[Point]) -> Vec<Point> {
+    let position = |p: &Point| [p.x(), p.y(), p.z()].map(|c| c as f32);
+    let controls = points.iter().map(position).collect::<Vec<_>>();
+    let control = |i: isize| controls[i.clamp(0, controls.len() as isize - 1) as usize];
+    let min = |a: usize| controls.iter().map(|c| c[a]).fold(f32::INFINITY, f32::min);
+    let max = |a: usize| controls.iter().map(|c| c[a]).fold(0.0, f32::max);
+
+    let mut samples = points.first().copied().into_iter().collect::<Vec<_>>();
+    for i in 0..controls.len().saturating_sub(1) as isize {
+        let [p0, p1, p2, p3] = [control(i - 1), control(i), control(i + 1), control(i + 2)];
+        let steps = (0..3).map(|a| (p2[a] - p1[a]).abs()).fold(1.0, f32::max) as usize * 2;
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let at = |a: usize| {
+                0.5 * (2.0 * p1[a]
+                    + (p2[a] - p0[a]) * t
+                    + (2.0 * p0[a] - 5.0 * p1[a] + 4.0 * p2[a] - p3[a]) * t * t
+                    + (3.0 * p1[a] - p0[a] - 3.0 * p2[a] + p3[a]) * t * t * t)
+            };
+            let cell = |a: usize| at(a).round().clamp(min(a), max(a)) as usize;
+            let point = Point::new(cell(0), cell(1), cell(2));
+            if samples.last() != Some(&point) {
+                samples.push(point);
+            }
+        }
+    }
+    samples
+}
+
+/// Lays tiles matched by `selector` along a path. The faces of the path cells that point to
+/// the previous and next cell have to carry `socket`, so the tiles connect along the path.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PathConstraint<D> {
+    pub shape: PathShape,
+    pub selector: TileSelector<D>,
+    pub socket: String,
+}
+
+impl<D> PathConstraint<D> {
+    pub fn new(shape: PathShape, selector: TileSelector<D>, socket: &str) -> Self {
+        Self {
+            shape,
+            selector,
+            socket: socket.to_string(),
+        }
+    }
+}
+
+/// A region of the grid and the tiles it may hold.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Zone<D> {
+    pub name: String,
+    pub tiles: Vec<TileSelector<D>>,
+}
+
+impl<D> Zone<D> {
+    pub fn new(name: &str, tiles: Vec<TileSelector<D>>) -> Self {
+        Self {
+            name: name.to_string(),
+            tiles,
+        }
+    }
+}
+
+/// Assigns every cell of the grid to one of `zones` through the `layout`. Cells next to a
+/// cell of another zone may also hold the `transitions`, usually selected by the socket the
+/// two zones share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneMap<D> {
+    pub zones: Vec<Zone<D>>,
+    pub layout: ZoneLayout,
+    pub transitions: Vec<TileSelector<D>>,
+}
+
+impl<D> ZoneMap<D> {
+    pub fn new(zones: Vec<Zone<D>>, layout: ZoneLayout, transitions: Vec<TileSelector<D>>) -> Self {
+        Self {
+            zones,
+            layout,
+            transitions,
+        }
+    }
+}
+
+/// A zone map resolved to variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledZones {
+    pub layout: ZoneLayout,
+    /// Per zone, the variants it may hold.
+    pub zones: Vec<BitVec>,
+    pub transitions: BitVec,
+}
+
+impl CompiledZones {
+    /// The variants allowed at `point`. A point outside of the layout or in an unknown zone
+    /// allows none.
+    pub fn domain(&self, point: Point) -> BitVec {
+        let zone = Some(point)
+            .filter(|point| self.layout.dimensions().contains(*point))
+            .and_then(|point| self.zones.get(self.layout.zone(point)));
+        let zone = match zone {
+            Some(zone) => zone.clone(),
+            None => return BitVec::repeat(false, self.transitions.len()),
+        };
+        if self.layout.is_border(point) {
+            zone | self.transitions.clone()
+        } else {
+            zone
+        }
+    }
+}
+
+/// Scales the weight of the tiles matched by `selector` per cell. The factor of a cell
+/// multiplies the weight of the tile, fields that match the same tile multiply as well.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WeightField<D> {
+    pub selector: TileSelector<D>,
+    pub map: WeightMap,
+}
+
+impl<D> WeightField<D> {
+    pub fn new(selector: TileSelector<D>, map: WeightMap) -> Self {
+        Self { selector, map }
+    }
+}
+
+/// Where the factors of a [`WeightField`] come from.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum WeightMap {
+    Gradient(Gradient),
+    /// A factor per cell, it has to have the dimensions of the description.
+    #[serde(skip)]
+    Grid(WeightGrid),
+}
+
+impl WeightMap {
+    /// A grid with the factor of every point given by `factor`.
+    pub fn from_fn<F: Fn(Point) -> f32>(dimensions: Dimensions, factor: F) -> Self {
+        WeightMap::Grid(WeightGrid::from_fn(dimensions, factor))
+    }
+
+    /// The factor at `point`. Negative factors count as zero, and a grid leaves the points
+    /// outside of it unchanged.
+    pub fn factor(&self, point: Point, dimensions: Dimensions) -> f32 {
+        let factor = match self {
+            WeightMap::Gradient(gradient) => gradient.factor(point, dimensions),
+            WeightMap::Grid(grid) if grid.dimensions().contains(point) => grid.get(point),
+            WeightMap::Grid(_) => 1.0,
+        };
+        factor.max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// A factor that changes linearly across the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Gradient {
+    /// From `from` in the first cell along `axis` to `to` in the last one.
+    Linear { axis: Axis, from: f32, to: f32 },
+    /// From `centre` in the horizontal centre of the grid to `edge` at its outer ring.
+    Radial { centre: f32, edge: f32 },
+}
+
+impl Gradient {
+    pub fn factor(&self, point: Point, dimensions: Dimensions) -> f32 {
+        // Position of `at` between the first and last cell of an axis, from 0 to 1.
+        let along = |at: usize, size: usize| {
+            if size > 1 {
+                at as f32 / (size - 1) as f32
+            } else {
+                0.0
+            }
+        };
+
+        match *self {
+            Gradient::Linear { axis, from, to } => {
+                let t = match axis {
+                    Axis::X => along(point.x(), dimensions.width()),
+                    Axis::Y => along(point.y(), dimensions.height()),
+                    Axis::Z => along(point.z(), dimensions.depth()),
+                };
+                from + (to - from) * t
+            }
+            Gradient::Radial { centre, edge } => {
+                let x = along(point.x(), dimensions.width()) * 2.0 - 1.0;
+                let z = along(point.z(), dimensions.depth()) * 2.0 - 1.0;
+                let t = x.abs().max(z.abs());
+                centre + (edge - centre) * t
+            }
+        }
+    }
+}
+
+/// A weight field resolved to variants and a factor per cell, indexed by [`Point::id`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledWeightField {
+    pub variants: BitVec,
+    pub factors: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledConnectivity {
+    /// The selector of the constraint, for error messages.
+    pub selector: String,
+    pub variants: BitVec,
+    pub anchors: Vec<Point>,
 }
 
+/// A count constraint resolved to variants and absolute cell counts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledCount {
+    /// The selector of the constraint, for error messages.
+    pub selector: String,
+    pub variants: BitVec,
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Gameplay data attached to a tile, e.g. `walkable`, `interior` or a movement cost.
+/// It is not used by the solver, but is carried through to the [`crate::Solution`].
+pub type TileMetadata = HashMap<String, MetadataValue>;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum MetadataValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl MetadataValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            MetadataValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MetadataValue::Int(i) => Some(*i as f64),
+            MetadataValue::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetadataValue::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Forces the tile with `id` in the given rotation at `point` before solving.
+#[derive(Debug, Clone)]
 pub struct FixedTile<D> {
     pub point: Point,
     pub rotation: TileRotation,
-    pub tile: Tile<D>,
+    pub id: D,
 }
 
 impl<D> FixedTile<D> {
-    pub fn new(point: Point, rotation: TileRotation, tile: Tile<D>) -> Self {
+    pub fn new(point: Point, rotation: TileRotation, id: D) -> Self {
         Self {
             point,
             rotation,
-            tile,
+            id,
         }
     }
 }
@@ -103,6 +916,13 @@ pub struct HorizontalConnection {
 }
 
 impl HorizontalConnection {
+    /// Whether `other` is this socket seen in a mirror.
+    pub fn is_mirror_of(&self, other: &Self) -> bool {
+        self.connection == other.connection
+            && self.symmetry == other.symmetry
+            && (self.symmetry || self.flipped != other.flipped)
+    }
+
     pub fn is_connected(&self, other: &Self, self_flipped: bool, other_flipped: bool) -> bool {
         if self.connection != other.connection {
             return false;
@@ -131,6 +951,8 @@ pub struct TileData {
     pub tile_index: usize,
     pub rotation: TileRotation,
     pub flipped: bool,
+    /// Offset of the cell inside the rotated footprint of a multi-cell tile.
+    pub part: Point,
 }
 
 impl TileData {
@@ -139,22 +961,54 @@ impl TileData {
             tile_index: index,
             rotation,
             flipped,
+            part: Point::default(),
         }
     }
+
+    /// Whether this is the cell a multi-cell tile is anchored in.
+    pub fn is_anchor(&self) -> bool {
+        self.part == Point::default()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct CompiledDescription {
     // initial_grid: FieldGrid,
     pub dimensions: Dimensions,
 
     pub transformation: Vec<TileData>, // id -> (tile_id, rotation, flipped) from problemdescription + rotation
+    pub weights: Vec<f32>,
+    /// Per variant, the placement rules of its tile.
+    pub placement: Vec<Placement>,
+    /// The variants of decorative tiles, which start out removed from every cell.
+    pub decorative: BitVec,
     pub up: Vec<BitVec>,
     pub down: Vec<BitVec>,
     pub left: Vec<BitVec>,
     pub right: Vec<BitVec>,
     pub forward: Vec<BitVec>,
     pub backward: Vec<BitVec>,
+
+    /// Per direction (in [`Direction::ALL`] order), the variants that are allowed in a cell
+    /// whose neighbour in that direction lies outside of the grid.
+    pub boundary: Vec<BitVec>,
+
+    /// The active cells, `None` when the whole box is generated.
+    pub mask: Option<Mask>,
+    /// Like `boundary`, for cells whose neighbour in that direction is masked out.
+    pub mask_boundary: Vec<BitVec>,
+
+    /// Domains forced at specific points before solving, from fixed tiles and paths.
+    pub fixed: Vec<(Point, BitVec)>,
+
+    pub counts: Vec<CompiledCount>,
+    pub connectivity: Vec<CompiledConnectivity>,
+    pub weight_fields: Vec<CompiledWeightField>,
+    pub height_guide: Option<CompiledHeightGuide>,
+    pub zones: Option<CompiledZones>,
+    pub prefabs: Vec<CompiledPrefab>,
+    pub symmetry: Vec<CompiledSymmetry>,
+    pub distribution: Option<CompiledDistribution>,
 }
 
 macro_rules! directional {
@@ -185,12 +1039,71 @@ impl CompiledDescription {
         &self.transformation[index]
     }
 
+    pub fn weight(&self, index: usize) -> f32 {
+        self.weights[index]
+    }
+
+    /// The weight of the variant at `point`, scaled by the weight fields that match it.
+    pub fn weight_at(&self, index: usize, point: Point) -> f32 {
+        let cell = point.id(self.dimensions);
+        self.weight_fields
+            .iter()
+            .filter(|field| field.variants[index])
+            .fold(self.weights[index], |weight, field| {
+                weight * field.factors[cell]
+            })
+    }
+
+    /// The variants whose placement rules, zone and height guide allow them at `point`.
+    pub fn placement(&self, point: Point) -> BitVec {
+        let mut allowed: BitVec = self
+            .placement
+            .iter()
+            .map(|placement| placement.allows(point, self.dimensions))
+            .collect();
+        if let Some(zones) = &self.zones {
+            allowed &= zones.domain(point);
+        }
+
+        match &self.height_guide {
+            Some(guide) if point.y() < guide.heightmap.height(point.x(), point.z()) => {
+                allowed & guide.below.clone()
+            }
+            Some(guide) => allowed & guide.above.clone(),
+            None => allowed,
+        }
+    }
+
     directional!(up);
     directional!(down);
     directional!(backward);
     directional!(forward);
     directional!(left);
     directional!(right);
+
+    pub fn boundary(&self, direction: Direction) -> &BitVec {
+        &self.boundary[direction.index()]
+    }
+
+    pub fn mask_boundary(&self, direction: Direction) -> &BitVec {
+        &self.mask_boundary[direction.index()]
+    }
+
+    /// A grid where every active cell can still hold every variant.
+    pub fn grid(&self) -> FieldGrid {
+        FieldGrid::new(self.dimensions, !self.decorative.clone()).with_mask(self.mask.clone())
+    }
+
+    pub fn direction(&self, direction: Direction, index: usize) -> &BitVec {
+        match direction {
+            Direction::Up => self.up(index),
+            Direction::Down => self.down(index),
+            Direction::Left => self.left(index),
+            Direction::Right => self.right(index),
+            Direction::Forward => self.forward(index),
+            Direction::Backward => self.backward(index),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -199,28 +1112,188 @@ pub struct ProblemDescription<D> {
     pub dimensions: Dimensions,
 
     pub tiles: Vec<Tile<D>>,
-    // pub fixed: Vec<FixedTile<D>>,
+    pub fixed: Vec<FixedTile<D>>,
+    pub counts: Vec<CountConstraint<D>>,
+    pub connectivity: Vec<ConnectivityConstraint<D>>,
+    /// Restricts generation to the active cells of the mask.
+    pub mask: Option<Mask>,
+    /// The tile that masked out cells act as, defaults to the boundary of the grid.
+    pub mask_tile: Option<D>,
+    pub weight_fields: Vec<WeightField<D>>,
+    pub height_guide: Option<HeightGuide<D>>,
+    pub zones: Option<ZoneMap<D>>,
+    pub paths: Vec<PathConstraint<D>>,
+    pub prefabs: Vec<PrefabPlacement<D>>,
+    /// Symmetries the whole output has to keep.
+    pub symmetry: Vec<Symmetry>,
+    pub distribution: Option<Distribution<D>>,
+}
+
+impl<D> Default for ProblemDescription<D> {
+    fn default() -> Self {
+        Self {
+            connections: Vec::new(),
+            dimensions: Dimensions::default(),
+            tiles: Vec::new(),
+            fixed: Vec::new(),
+            counts: Vec::new(),
+            connectivity: Vec::new(),
+            mask: None,
+            mask_tile: None,
+            weight_fields: Vec::new(),
+            height_guide: None,
+            zones: None,
+            paths: Vec::new(),
+            prefabs: Vec::new(),
+            symmetry: Vec::new(),
+            distribution: None,
+        }
+    }
 }
 
 macro_rules! tile_data {
     ($a:expr, $b:expr, $c:expr) => {
-        TileData {
-            tile_index: $a,
-            rotation: $b,
-            flipped: $c,
-        }
+        TileData::new($a, $b, $c)
     };
 }
 
-impl<D> ProblemDescription<D> {
+impl<D: PartialEq + Debug> ProblemDescription<D> {
+    /// Checks the parts of the description that would make solving meaningless.
+    pub fn check(&self) -> Result<(), ProblemError> {
+        if self.dimensions.len() == 0 {
+            return Err(ProblemError::Dimensions(self.dimensions));
+        }
+
+        if let Some(mask) = &self.mask {
+            if mask.dimensions() != self.dimensions {
+                return Err(ProblemError::MaskDimensions(mask.dimensions()));
+            }
+        }
+        if let Some(id) = &self.mask_tile {
+            if !self.tiles.iter().any(|tile| tile.id == *id) {
+                return Err(ProblemError::UnknownMaskTile);
+            }
+        }
+        if let Some(guide) = &self.height_guide {
+            let heightmap = &guide.heightmap;
+            if (heightmap.width(), heightmap.depth())
+                != (self.dimensions.width(), self.dimensions.depth())
+            {
+                return Err(ProblemError::HeightmapDimensions(
+                    heightmap.width(),
+                    heightmap.depth(),
+                ));
+            }
+        }
+        if let Some(zones) = &self.zones {
+            let layout = &zones.layout;
+            if layout.dimensions() != self.dimensions {
+                return Err(ProblemError::ZoneMapDimensions(layout.dimensions()));
+            }
+            if let Some(point) = self
+                .dimensions
+                .points()
+                .find(|point| layout.zone(*point) >= zones.zones.len())
+            {
+                return Err(ProblemError::UnknownZone(point));
+            }
+        }
+        for field in &self.weight_fields {
+            if let WeightMap::Grid(grid) = &field.map {
+                if grid.dimensions() != self.dimensions {
+                    return Err(ProblemError::WeightFieldDimensions(grid.dimensions()));
+                }
+            }
+        }
+        let active = |point: Point| match &self.mask {
+            Some(mask) => mask.is_active(point),
+            None => self.dimensions.contains(point),
+        };
+
+        for fixed in &self.fixed {
+            if !active(fixed.point) {
+                return Err(ProblemError::FixedOutOfBounds(fixed.point));
+            }
+            if !self.tiles.iter().any(|tile| tile.id == fixed.id) {
+                return Err(ProblemError::UnknownFixedTile(fixed.point));
+            }
+        }
+
+        for (index, placement) in self.prefabs.iter().enumerate() {
+            let prefab = &placement.prefab;
+            if prefab
+                .dimensions()
+                .points()
+                .filter_map(|point| prefab.get(point))
+                .any(|(id, _)| !self.tiles.iter().any(|tile| tile.id == *id))
+            {
+                return Err(ProblemError::UnknownPrefabTile(index));
+            }
+            if let Some(at) = placement.at {
+                let fits = placement.rotations().into_iter().any(|rotation| {
+                    let (size, _) = prefab.rotated(rotation);
+                    size.points()
+                        .all(|p| active(Point::new(at.x() + p.x(), at.y() + p.y(), at.z() + p.z())))
+                });
+                if !fits {
+                    return Err(ProblemError::PrefabOutOfBounds(index));
+                }
+            }
+        }
+
+        if let Some(index) = self.distribution.iter().find_map(|distribution| {
+            distribution
+                .targets
+                .iter()
+                .position(|(id, _)| !self.tiles.iter().any(|tile| tile.id == *id))
+        }) {
+            return Err(ProblemError::UnknownDistributionTile(index));
+        }
+        if self.symmetry.contains(&Symmetry::Rotate90)
+            && self.dimensions.width() != self.dimensions.depth()
+        {
+            return Err(ProblemError::SymmetryDimensions(self.dimensions));
+        }
+
+        for path in &self.paths {
+            if let Some(cell) = path.shape.cells().into_iter().find(|cell| !active(*cell)) {
+                return Err(ProblemError::PathOutOfBounds(cell));
+            }
+        }
+
+        for connectivity in &self.connectivity {
+            if let Some(anchor) = connectivity
+                .anchors
+                .iter()
+                .find(|anchor| !active(**anchor))
+            {
+                return Err(ProblemError::AnchorOutOfBounds(*anchor));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the description into adjacency bitvecs, with variants that can never be
+    /// placed already removed.
     pub fn compile(&self) -> CompiledDescription {
+        self.compile_with_report().0
+    }
+
+    /// Like [`ProblemDescription::compile`], but also returns the variants that were pruned.
+    pub fn compile_with_report(&self) -> (CompiledDescription, Vec<TileData>) {
+        self.compile_all().prune()
+    }
+
+    /// Compiles every variant of every tile, without pruning.
+    pub fn compile_all(&self) -> CompiledDescription {
         let count = self.tiles.len() * 4;
         let mut transformation = Vec::with_capacity(count);
 
         // let initial_grid = FieldGrid::new(self.dimensions, BitVec::repeat(true, count));
 
-        for (index, _) in self.tiles.iter().enumerate() {
-            transformation.extend([
+        for (index, tile) in self.tiles.iter().enumerate() {
+            for data in [
                 tile_data!(index, TileRotation::R0, false),
                 tile_data!(index, TileRotation::R90, false),
                 tile_data!(index, TileRotation::R180, false),
@@ -229,20 +1302,176 @@ impl<D> ProblemDescription<D> {
                 // tile_data!(index, TileRotation::R90, true),
                 // tile_data!(index, TileRotation::R180, true),
                 // tile_data!(index, TileRotation::R270, true),
-            ]);
+            ] {
+                let footprint = tile.rotated_footprint(data.rotation);
+                transformation.extend(footprint.points().map(|part| TileData {
+                    part,
+                    ..data.clone()
+                }));
+            }
         }
 
-        let up = collect_up_connections(&self.tiles, &transformation);
-        let down = collect_down_connections(&self.tiles, &transformation);
-        let left = collect_direction_connections(&self.tiles, &transformation, TileRotation::R270);
-        let right = collect_direction_connections(&self.tiles, &transformation, TileRotation::R90);
-        let forward = collect_direction_connections(&self.tiles, &transformation, TileRotation::R0);
-        let backward =
-            collect_direction_connections(&self.tiles, &transformation, TileRotation::R180);
+        let weights = transformation
+            .iter()
+            .map(|td: &TileData| self.tiles[td.tile_index].weight)
+            .collect();
+        let placement = transformation
+            .iter()
+            .map(|td: &TileData| self.tiles[td.tile_index].placement.clone())
+            .collect();
+        let decorative = transformation
+            .iter()
+            .map(|td: &TileData| self.tiles[td.tile_index].decorative)
+            .collect();
+
+        let mut connections = [
+            collect_up_connections(&self.tiles, &transformation),
+            collect_down_connections(&self.tiles, &transformation),
+            collect_direction_connections(&self.tiles, &transformation, TileRotation::R270),
+            collect_direction_connections(&self.tiles, &transformation, TileRotation::R90),
+            collect_direction_connections(&self.tiles, &transformation, TileRotation::R0),
+            collect_direction_connections(&self.tiles, &transformation, TileRotation::R180),
+        ];
+        lock_footprints(&self.tiles, &transformation, &mut connections);
+        let [up, down, left, right, forward, backward] = connections;
+
+        // The variants that accept the `outside` variant as their neighbour in each direction.
+        let outside_domains = |outside: usize, open_floor: bool| -> Vec<BitVec> {
+            Direction::ALL
+                .iter()
+                .map(|direction| {
+                    let connections = match direction {
+                        Direction::Up => &up,
+                        Direction::Down if open_floor => {
+                            return BitVec::repeat(true, transformation.len())
+                        }
+                        Direction::Down => &down,
+                        Direction::Left => &left,
+                        Direction::Right => &right,
+                        Direction::Forward => &forward,
+                        Direction::Backward => &backward,
+                    };
+                    connections
+                        .iter()
+                        .map(|x| x.get(outside).map(|b| *b).unwrap_or_default())
+                        .collect()
+                })
+                .collect()
+        };
+
+        // The first tile is what lies outside of the grid, the floor is left open.
+        let boundary = outside_domains(0, true);
+        let mask_boundary = match self.mask_tile.as_ref().and_then(|id| {
+            transformation.iter().position(|td: &TileData| {
+                self.tiles[td.tile_index].id == *id && td.rotation == TileRotation::R0
+            })
+        }) {
+            Some(outside) => outside_domains(outside, false),
+            None => boundary.clone(),
+        };
+
+        let mut fixed = self
+            .fixed
+            .iter()
+            .map(|fixed| {
+                let domain = transformation
+                    .iter()
+                    .map(|td: &TileData| {
+                        self.tiles[td.tile_index].id == fixed.id
+                            && td.rotation == fixed.rotation
+                            && td.is_anchor()
+                    })
+                    .collect();
+                (fixed.point, domain)
+            })
+            .collect::<Vec<_>>();
+        for path in &self.paths {
+            fixed.extend(self.compile_path(path, &transformation));
+        }
+        let prefabs = self
+            .prefabs
+            .iter()
+            .map(|placement| self.compile_prefab(placement, &transformation))
+            .collect();
+        let symmetry = self
+            .symmetry
+            .iter()
+            .map(|symmetry| self.compile_symmetry(*symmetry, &transformation))
+            .collect();
+        let distribution = self
+            .distribution
+            .as_ref()
+            .map(|distribution| CompiledDistribution {
+                targets: distribution
+                    .targets
+                    .iter()
+                    .map(|(id, share)| {
+                        let variants = transformation
+                            .iter()
+                            .map(|td| self.tiles[td.tile_index].id == *id)
+                            .collect();
+                        (variants, *share)
+                    })
+                    .collect(),
+                bounds: self.counts.len(),
+                cells: self.active_cells(),
+            });
+
+        let counts = self.compile_counts(&transformation);
+        let connectivity = self
+            .connectivity
+            .iter()
+            .map(|connectivity| CompiledConnectivity {
+                selector: format!("{:?}", connectivity.selector),
+                variants: transformation
+                    .iter()
+                    .map(|td| connectivity.selector.matches(&self.tiles[td.tile_index]))
+                    .collect(),
+                anchors: connectivity.anchors.clone(),
+            })
+            .collect();
+        let weight_fields = self
+            .weight_fields
+            .iter()
+            .map(|field| CompiledWeightField {
+                variants: transformation
+                    .iter()
+                    .map(|td| field.selector.matches(&self.tiles[td.tile_index]))
+                    .collect(),
+                factors: self.weight_factors(&field.map),
+            })
+            .collect();
+        let matches_any = |selectors: &[TileSelector<D>]| -> BitVec {
+            transformation
+                .iter()
+                .map(|td| {
+                    selectors
+                        .iter()
+                        .any(|selector| selector.matches(&self.tiles[td.tile_index]))
+                })
+                .collect()
+        };
+        let height_guide = self.height_guide.as_ref().map(|guide| CompiledHeightGuide {
+            heightmap: guide.heightmap.clone(),
+            below: matches_any(&guide.below),
+            above: matches_any(&guide.above),
+        });
+        let zones = self.zones.as_ref().map(|zones| CompiledZones {
+            layout: zones.layout.clone(),
+            zones: zones
+                .zones
+                .iter()
+                .map(|zone| matches_any(&zone.tiles))
+                .collect(),
+            transitions: matches_any(&zones.transitions),
+        });
 
         CompiledDescription {
             dimensions: self.dimensions,
             transformation,
+            weights,
+            placement,
+            decorative,
             // initial_grid,
             up,
             down,
@@ -250,6 +1479,449 @@ impl<D> ProblemDescription<D> {
             right,
             forward,
             backward,
+            boundary,
+            mask: self.mask.clone(),
+            mask_boundary,
+            fixed,
+            counts,
+            connectivity,
+            weight_fields,
+            height_guide,
+            zones,
+            prefabs,
+            symmetry,
+            distribution,
+        }
+    }
+
+    fn compile_prefab(
+        &self,
+        placement: &PrefabPlacement<D>,
+        transformation: &[TileData],
+    ) -> CompiledPrefab {
+        CompiledPrefab {
+            at: placement.at,
+            rotations: placement
+                .rotations()
+                .into_iter()
+                .map(|rotation| {
+                    let (dimensions, cells) = placement.prefab.rotated(rotation);
+                    let cells = cells
+                        .into_iter()
+                        .map(|(point, id, rotation)| {
+                            let domain = transformation
+                                .iter()
+                                .map(|td| {
+                                    self.tiles[td.tile_index].id == *id
+                                        && td.rotation == rotation
+                                        && td.is_anchor()
+                                })
+                                .collect();
+                            (point, domain)
+                        })
+                        .collect();
+                    (dimensions, cells)
+                })
+                .collect(),
+        }
+    }
+
+    /// Finds the image of every variant. Rotations keep the sockets of a tile, the mirror
+    /// image is a variant, preferably of the same tile, that shows the mirrored sockets on the
+    /// mirrored sides and the same sockets above and below.
+    fn compile_symmetry(
+        &self,
+        symmetry: Symmetry,
+        transformation: &[TileData],
+    ) -> CompiledSymmetry {
+        let images = transformation
+            .iter()
+            .map(|td| {
+                let tile = &self.tiles[td.tile_index];
+                let footprint = tile.rotated_footprint(td.rotation);
+                let part = symmetry.image(td.part, footprint);
+                let mirrored = |other: &TileData| {
+                    let image = &self.tiles[other.tile_index];
+                    other.part == part
+                        && other.flipped == td.flipped
+                        && image.rotated_footprint(other.rotation) == footprint
+                        && [Direction::Up, Direction::Down]
+                            .into_iter()
+                            .all(|direction| {
+                                image.connections(direction) == tile.connections(direction)
+                            })
+                        && [
+                            Direction::Left,
+                            Direction::Right,
+                            Direction::Forward,
+                            Direction::Backward,
+                        ]
+                        .into_iter()
+                        .all(|direction| {
+                            let face = image.rotated_face(direction, other.rotation).unwrap();
+                            let original = tile
+                                .rotated_face(symmetry.side(direction), td.rotation)
+                                .unwrap();
+                            face.len() == original.len()
+                                && face.iter().zip(original).all(|(a, b)| a.is_mirror_of(b))
+                        })
+                };
+
+                let same_tile = symmetry
+                    .rotations(td.rotation)
+                    .into_iter()
+                    .find_map(|rotation| {
+                        transformation.iter().position(|other| {
+                            if symmetry.is_mirror() {
+                                other.tile_index == td.tile_index
+                                    && other.rotation == rotation
+                                    && mirrored(other)
+                            } else {
+                                *other
+                                    == TileData {
+                                        rotation,
+                                        part,
+                                        ..td.clone()
+                                    }
+                            }
+                        })
+                    });
+                same_tile.or_else(|| {
+                    transformation
+                        .iter()
+                        .position(|other| symmetry.is_mirror() && mirrored(other))
+                })
+            })
+            .collect();
+
+        CompiledSymmetry { symmetry, images }
+    }
+
+    /// The domain of every cell of the path: tiles of the path whose faces towards the
+    /// neighbouring path cells carry the socket of the path.
+    fn compile_path(
+        &self,
+        path: &PathConstraint<D>,
+        transformation: &[TileData],
+    ) -> Vec<(Point, BitVec)> {
+        let cells = path.shape.cells();
+        let towards = |from: Point, to: Point| {
+            Direction::ALL
+                .into_iter()
+                .find(|d| from.neighbour(*d, self.dimensions) == Some(to))
+        };
+
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let along = [i.checked_sub(1), Some(i + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|j| cells.get(j))
+                    .filter_map(|other| towards(*cell, *other))
+                    .collect::<Vec<_>>();
+                let domain = transformation
+                    .iter()
+                    .map(|td| {
+                        let tile = &self.tiles[td.tile_index];
+                        path.selector.matches(tile)
+                            && along.iter().all(|d| {
+                                tile.rotated_connections(*d, td.rotation)
+                                    .contains(&path.socket.as_str())
+                            })
+                    })
+                    .collect();
+                (*cell, domain)
+            })
+            .collect()
+    }
+
+    /// The factor of every cell, indexed by [`Point::id`].
+    fn weight_factors(&self, map: &WeightMap) -> Vec<f32> {
+        let mut factors = vec![1.0; self.dimensions.len()];
+        for point in self.dimensions.points() {
+            factors[point.id(self.dimensions)] = map.factor(point, self.dimensions);
+        }
+        factors
+    }
+
+    /// The number of cells the mask leaves active.
+    fn active_cells(&self) -> usize {
+        match &self.mask {
+            Some(mask) => self
+                .dimensions
+                .points()
+                .filter(|p| mask.is_active(*p))
+                .count(),
+            None => self.dimensions.len(),
+        }
+    }
+
+    fn compile_counts(&self, transformation: &[TileData]) -> Vec<CompiledCount> {
+        let cells = self.active_cells();
+        let resolve = |amount: Amount, round: fn(f32) -> f32| match amount {
+            Amount::Cells(count) => count,
+            Amount::Fraction(fraction) => round(fraction * cells as f32) as usize,
+        };
+
+        let bounds = self.distribution.iter().flat_map(|distribution| {
+            distribution
+                .targets
+                .iter()
+                .map(move |(id, share)| CompiledCount {
+                    selector: format!("{:?}", TileSelector::Tile(id)),
+                    variants: transformation
+                        .iter()
+                        .map(|td| self.tiles[td.tile_index].id == *id && td.is_anchor())
+                        .collect(),
+                    min: resolve(Amount::Fraction(share - distribution.tolerance), f32::ceil),
+                    max: resolve(Amount::Fraction(share + distribution.tolerance), f32::floor)
+                        .min(cells),
+                })
+        });
+
+        self.counts
+            .iter()
+            .map(|count| CompiledCount {
+                selector: format!("{:?}", count.selector),
+                variants: transformation
+                    .iter()
+                    .map(|td| td.is_anchor() && count.selector.matches(&self.tiles[td.tile_index]))
+                    .collect(),
+                min: count.min.map(|a| resolve(a, f32::ceil)).unwrap_or(0),
+                max: count.max.map(|a| resolve(a, f32::floor)).unwrap_or(cells),
+            })
+            .chain(bounds)
+            .collect()
+    }
+}
+
+impl Adjacency for CompiledDescription {
+    fn variants(&self) -> usize {
+        self.len()
+    }
+
+    fn allowed(&self, direction: usize, variant: usize) -> &BitVec {
+        self.direction(Direction::ALL[direction], variant)
+    }
+
+    fn weight(&self, variant: usize) -> f32 {
+        self.weights[variant]
+    }
+}
+
+impl CompiledDescription {
+    /// Removes variants that can not appear in any cell, because some direction has no
+    /// remaining neighbour to support them and that side can not be placed against the
+    /// boundary either. With a mask, a side may also face the mask tile. Decorative variants
+    /// support no other variant, but are kept. Runs to a fixpoint and returns the removed
+    /// variants.
+    pub fn prune(&self) -> (CompiledDescription, Vec<TileData>) {
+        if self.dimensions.len() == 0 {
+            return (self.clone(), Vec::new());
+        }
+
+        let mut alive = !self.decorative.clone();
+        loop {
+            let dead = alive
+                .iter_ones()
+                .filter(|v| !self.is_viable(*v, &alive))
+                .collect::<Vec<_>>();
+
+            if dead.is_empty() {
+                break;
+            }
+            for v in dead {
+                alive.set(v, false);
+            }
+        }
+
+        let keep = alive | self.decorative.clone();
+        let removed = keep.iter_zeros().map(|v| self.data(v).clone()).collect();
+
+        (self.retain(&keep), removed)
+    }
+
+    fn is_viable(&self, variant: usize, alive: &BitVec) -> bool {
+        let axes = [
+            (Direction::Down, Direction::Up, self.dimensions.height()),
+            (Direction::Left, Direction::Right, self.dimensions.width()),
+            (Direction::Backward, Direction::Forward, self.dimensions.depth()),
+        ];
+
+        axes.iter().all(|(negative, positive, size)| {
+            let supported =
+                |d: Direction| (self.direction(d, variant).clone() & alive.clone()).any();
+            let outside = |d: Direction| {
+                self.boundary(d)[variant] || (self.mask.is_some() && self.mask_boundary(d)[variant])
+            };
+
+            // A masked cell can have the mask on both sides, whatever the size of the grid.
+            if self.mask.is_some() {
+                return (outside(*negative) || supported(*negative))
+                    && (outside(*positive) || supported(*positive));
+            }
+            match size {
+                1 => outside(*negative) && outside(*positive),
+                2 => {
+                    (outside(*negative) && supported(*positive))
+                        || (supported(*negative) && outside(*positive))
+                }
+                _ => {
+                    (outside(*negative) || supported(*negative))
+                        && (outside(*positive) || supported(*positive))
+                        && (supported(*negative) || supported(*positive))
+                }
+            }
+        })
+    }
+
+    /// Keeps only the variants set in `keep`, renumbering them in order.
+    fn retain(&self, keep: &BitVec) -> CompiledDescription {
+        let filter = |x: &BitVec| -> BitVec { keep.iter_ones().map(|i| x[i]).collect() };
+        let filter_all = |rules: &Vec<BitVec>| -> Vec<BitVec> {
+            keep.iter_ones().map(|i| filter(&rules[i])).collect()
+        };
+
+        CompiledDescription {
+            dimensions: self.dimensions,
+            transformation: keep
+                .iter_ones()
+                .map(|i| self.transformation[i].clone())
+                .collect(),
+            weights: keep.iter_ones().map(|i| self.weights[i]).collect(),
+            placement: keep
+                .iter_ones()
+                .map(|i| self.placement[i].clone())
+                .collect(),
+            decorative: filter(&self.decorative),
+            up: filter_all(&self.up),
+            down: filter_all(&self.down),
+            left: filter_all(&self.left),
+            right: filter_all(&self.right),
+            forward: filter_all(&self.forward),
+            backward: filter_all(&self.backward),
+            boundary: self.boundary.iter().map(filter).collect(),
+            mask: self.mask.clone(),
+            mask_boundary: self.mask_boundary.iter().map(filter).collect(),
+            fixed: self
+                .fixed
+                .iter()
+                .map(|(point, domain)| (*point, filter(domain)))
+                .collect(),
+            counts: self
+                .counts
+                .iter()
+                .map(|count| CompiledCount {
+                    variants: filter(&count.variants),
+                    ..count.clone()
+                })
+                .collect(),
+            connectivity: self
+                .connectivity
+                .iter()
+                .map(|connectivity| CompiledConnectivity {
+                    variants: filter(&connectivity.variants),
+                    ..connectivity.clone()
+                })
+                .collect(),
+            weight_fields: self
+                .weight_fields
+                .iter()
+                .map(|field| CompiledWeightField {
+                    variants: filter(&field.variants),
+                    factors: field.factors.clone(),
+                })
+                .collect(),
+            height_guide: self.height_guide.as_ref().map(|guide| CompiledHeightGuide {
+                heightmap: guide.heightmap.clone(),
+                below: filter(&guide.below),
+                above: filter(&guide.above),
+            }),
+            zones: self.zones.as_ref().map(|zones| CompiledZones {
+                layout: zones.layout.clone(),
+                zones: zones.zones.iter().map(filter).collect(),
+                transitions: filter(&zones.transitions),
+            }),
+            prefabs: self
+                .prefabs
+                .iter()
+                .map(|prefab| CompiledPrefab {
+                    at: prefab.at,
+                    rotations: prefab
+                        .rotations
+                        .iter()
+                        .map(|(dimensions, cells)| {
+                            let cells = cells
+                                .iter()
+                                .map(|(point, domain)| (*point, filter(domain)))
+                                .collect();
+                            (*dimensions, cells)
+                        })
+                        .collect(),
+                })
+                .collect(),
+            symmetry: self
+                .symmetry
+                .iter()
+                .map(|symmetry| CompiledSymmetry {
+                    symmetry: symmetry.symmetry,
+                    images: keep
+                        .iter_ones()
+                        .map(|i| {
+                            symmetry.images[i]
+                                .filter(|image| keep[*image])
+                                .map(|image| keep[..image].count_ones())
+                        })
+                        .collect(),
+                })
+                .collect(),
+            distribution: self
+                .distribution
+                .as_ref()
+                .map(|distribution| CompiledDistribution {
+                    targets: distribution
+                        .targets
+                        .iter()
+                        .map(|(variants, share)| (filter(variants), *share))
+                        .collect(),
+                    bounds: distribution.bounds,
+                    cells: distribution.cells,
+                }),
+        }
+    }
+}
+
+/// Links the cells of multi-cell tiles. A face inside the footprint only accepts the
+/// neighbouring part of the same tile, and no outer face accepts an inner face.
+fn lock_footprints<D>(
+    tiles: &[Tile<D>],
+    transformations: &[TileData],
+    connections: &mut [Vec<BitVec>; 6],
+) {
+    let inner = |td: &TileData, direction: Direction| {
+        td.part.neighbour(
+            direction,
+            tiles[td.tile_index].rotated_footprint(td.rotation),
+        )
+    };
+
+    for direction in Direction::ALL {
+        let facing_inner = transformations
+            .iter()
+            .map(|td| inner(td, direction.opposite()).is_some())
+            .collect::<BitVec>();
+
+        for (index, td) in transformations.iter().enumerate() {
+            let allowed = &mut connections[direction.index()][index];
+            *allowed = match inner(td, direction) {
+                Some(part) => transformations
+                    .iter()
+                    .map(|other| *other == TileData { part, ..td.clone() })
+                    .collect(),
+                None => allowed.clone() & !facing_inner.clone(),
+            };
         }
     }
 }
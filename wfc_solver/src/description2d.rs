@@ -0,0 +1,125 @@
+use crate::{
+    description::{
+        ConnectivityConstraint, CountConstraint, FixedTile, HorizontalConnection, PathConstraint,
+        Placement, ProblemDescription, Symmetry, Tile, TileMetadata, VerticalConnection,
+    },
+    utils::Dimensions,
+};
+use serde::Deserialize;
+
+/// The socket on the top and bottom of every tile once a 2D description is compiled.
+pub const PLANE_SOCKET: &str = "__plane";
+
+/// A tile with four sides. Rotation and flipping work as for [`Tile`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tile2d<D> {
+    pub id: D,
+    pub left: Vec<HorizontalConnection>,
+    pub right: Vec<HorizontalConnection>,
+    pub forward: Vec<HorizontalConnection>,
+    pub backward: Vec<HorizontalConnection>,
+
+    pub can_rotate: bool,
+    pub can_flip: bool,
+
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    #[serde(default)]
+    pub metadata: TileMetadata,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub placement: Placement,
+    #[serde(default)]
+    pub decorative: bool,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl<D> Tile2d<D> {
+    pub fn into_tile(self) -> Tile<D> {
+        let plane = || {
+            vec![VerticalConnection {
+                connection: PLANE_SOCKET.into(),
+            }]
+        };
+
+        Tile {
+            id: self.id,
+            up: plane(),
+            down: plane(),
+            left: self.left,
+            right: self.right,
+            forward: self.forward,
+            backward: self.backward,
+            can_rotate: self.can_rotate,
+            can_flip: self.can_flip,
+            weight: self.weight,
+            metadata: self.metadata,
+            tags: self.tags,
+            placement: self.placement,
+            footprint: (1, 1, 1),
+            decorative: self.decorative,
+        }
+    }
+}
+
+/// A problem on a `width` x `height` plane. It compiles to a description one cell high, the
+/// 2D `y` axis becomes `z`, so the tile at `(x, y)` ends up at `Point::new(x, 0, y)`.
+#[derive(Debug, Clone)]
+pub struct ProblemDescription2d<D> {
+    pub connections: Vec<String>,
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<Tile2d<D>>,
+    pub fixed: Vec<FixedTile<D>>,
+    pub counts: Vec<CountConstraint<D>>,
+    pub connectivity: Vec<ConnectivityConstraint<D>>,
+    /// Paths in 3D points, with the 2D `y` as `z`.
+    pub paths: Vec<PathConstraint<D>>,
+    /// Symmetries of the plane, `MirrorZ` mirrors the 2D `y` axis.
+    pub symmetry: Vec<Symmetry>,
+}
+
+impl<D> Default for ProblemDescription2d<D> {
+    fn default() -> Self {
+        Self {
+            connections: Vec::new(),
+            width: 0,
+            height: 0,
+            tiles: Vec::new(),
+            fixed: Vec::new(),
+            counts: Vec::new(),
+            connectivity: Vec::new(),
+            paths: Vec::new(),
+            symmetry: Vec::new(),
+        }
+    }
+}
+
+impl<D> ProblemDescription2d<D> {
+    pub fn into_3d(self) -> ProblemDescription<D> {
+        let mut connections = self.connections;
+        connections.push(PLANE_SOCKET.into());
+
+        ProblemDescription {
+            connections,
+            dimensions: Dimensions::new(self.width, 1, self.height),
+            tiles: self.tiles.into_iter().map(Tile2d::into_tile).collect(),
+            fixed: self.fixed,
+            counts: self.counts,
+            connectivity: self.connectivity,
+            mask: None,
+            mask_tile: None,
+            weight_fields: Vec::new(),
+            height_guide: None,
+            zones: None,
+            paths: self.paths,
+            prefabs: Vec::new(),
+            symmetry: self.symmetry,
+            distribution: None,
+        }
+    }
+}
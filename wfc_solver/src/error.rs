@@ -1,9 +1,126 @@
 use std::{error::Error, fmt::Display};
 
-#[derive(Debug)]
+use crate::{
+    topology::{HexDirection, HexPoint},
+    utils::{Dimensions, Direction, Point},
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProblemError {
     Unsatisfiable,
-    Dimensions,
+    /// A cell ran out of possible tiles.
+    Contradiction(Contradiction),
+    /// Every dimension has to be at least one.
+    Dimensions(Dimensions),
+    /// A cell of a hexagonal grid ran out of possible tiles, with the direction of, and the
+    /// neighbour that removed its last options.
+    HexContradiction(HexPoint, Option<(HexDirection, HexPoint)>),
+    /// A node of a graph ran out of possible tiles.
+    GraphContradiction(usize),
+    /// A graph edge refers to a node that is not in the graph.
+    UnknownNode(usize),
+    /// A graph edge uses a label that does not map to a direction.
+    UnknownEdgeLabel(String),
+    /// The mask does not have the dimensions of the description.
+    MaskDimensions(Dimensions),
+    /// The mask tile is not in the description.
+    UnknownMaskTile,
+    /// The heightmap is not as wide and deep as the grid.
+    HeightmapDimensions(usize, usize),
+    /// The zone map does not have the dimensions of the description.
+    ZoneMapDimensions(Dimensions),
+    /// A cell of the zone map refers to a zone that is not in the map.
+    UnknownZone(Point),
+    /// The grid of a weight field does not have the dimensions of the description.
+    WeightFieldDimensions(Dimensions),
+    /// A fixed tile lies outside of the grid or in a masked out cell.
+    FixedOutOfBounds(Point),
+    /// A fixed tile refers to a tile id that is not in the description.
+    UnknownFixedTile(Point),
+    /// A prefab refers to a tile id that is not in the description.
+    UnknownPrefabTile(usize),
+    /// There is no position and rotation at which the prefab fits.
+    PrefabUnplaceable(usize),
+    /// A prefab given a position does not fit inside the active cells there.
+    PrefabOutOfBounds(usize),
+    /// The tile a rewrite rule replaces with is not in the description, or has no variant in
+    /// the rotation of the replaced tile.
+    UnknownRewriteTile(usize),
+    /// A target of the distribution refers to a tile id that is not in the description.
+    UnknownDistributionTile(usize),
+    /// A quarter turn symmetry needs as many cells along x as along z.
+    SymmetryDimensions(Dimensions),
+    /// A cell of a path lies outside of the grid or in a masked out cell.
+    PathOutOfBounds(Point),
+    /// A storey of a layered problem is not as wide and deep as the first storey.
+    LayerDimensions(usize),
+    /// Solving a storey of a layered problem failed.
+    Layer(usize, Box<ProblemError>),
+    /// A count constraint can no longer be met.
+    Count(CountViolation),
+    /// A connectivity anchor lies outside of the grid or in a masked out cell.
+    AnchorOutOfBounds(Point),
+    /// Cells of a connectivity constraint can no longer be joined into one region.
+    Disconnected(Disconnection),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disconnection {
+    /// Index into the connectivity constraints of the description.
+    pub constraint: usize,
+    /// The selector of the constraint.
+    pub selector: String,
+    pub from: Point,
+    pub to: Point,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountViolation {
+    /// Index into the count constraints of the description.
+    pub constraint: usize,
+    /// The selector of the constraint.
+    pub selector: String,
+    /// Cells that certainly hold a matching tile.
+    pub assigned: usize,
+    /// Cells that could still hold a matching tile.
+    pub possible: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Where and why the domain of a cell became empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contradiction {
+    pub point: Point,
+    pub stage: SolveStage,
+    /// The direction of, and the neighbour that removed the last options of `point`.
+    /// `None` when the cell itself was restricted too far.
+    pub cause: Option<(Direction, Point)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveStage {
+    Placement,
+    Boundary,
+    FixedTiles,
+    Prefabs,
+    GlobalConstraints,
+    Collapse,
+}
+
+impl ProblemError {
+    /// Marks a contradiction as having happened during `stage`.
+    pub fn in_stage(self, stage: SolveStage) -> Self {
+        match self {
+            ProblemError::Contradiction(contradiction) => {
+                ProblemError::Contradiction(Contradiction {
+                    stage,
+                    ..contradiction
+                })
+            }
+            err => err,
+        }
+    }
 }
 
 impl Error for ProblemError {}
@@ -12,7 +129,143 @@ impl Display for ProblemError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ProblemError::Unsatisfiable => f.write_str("Cannot satisfy problem description"),
-            ProblemError::Dimensions => f.write_str("Invalid dimensions occured"),
+            ProblemError::Contradiction(contradiction) => contradiction.fmt(f),
+            ProblemError::Dimensions(dimensions) => write!(
+                f,
+                "Invalid dimensions {}x{}x{}",
+                dimensions.width(),
+                dimensions.height(),
+                dimensions.depth()
+            ),
+            ProblemError::HexContradiction(point, cause) => {
+                write!(f, "No tile fits at {:?}", point)?;
+                if let Some((direction, neighbour)) = cause {
+                    write!(
+                        f,
+                        ", constrained by {:?} neighbour {:?}",
+                        direction, neighbour
+                    )?;
+                }
+                Ok(())
+            }
+            ProblemError::GraphContradiction(node) => write!(f, "No tile fits at node {}", node),
+            ProblemError::UnknownNode(node) => write!(f, "Graph edge uses unknown node {}", node),
+            ProblemError::UnknownEdgeLabel(label) => {
+                write!(f, "Graph edge label {:?} has no direction", label)
+            }
+            ProblemError::MaskDimensions(dimensions) => write!(
+                f,
+                "Mask dimensions {}x{}x{} do not match the grid",
+                dimensions.width(),
+                dimensions.height(),
+                dimensions.depth()
+            ),
+            ProblemError::UnknownMaskTile => f.write_str("The mask tile is not in the description"),
+            ProblemError::HeightmapDimensions(width, depth) => write!(
+                f,
+                "Heightmap dimensions {}x{} do not match the grid",
+                width, depth
+            ),
+            ProblemError::ZoneMapDimensions(dimensions) => write!(
+                f,
+                "Zone map dimensions {}x{}x{} do not match the grid",
+                dimensions.width(),
+                dimensions.height(),
+                dimensions.depth()
+            ),
+            ProblemError::UnknownZone(point) => {
+                write!(f, "The zone at {:?} is not in the zone map", point)
+            }
+            ProblemError::WeightFieldDimensions(dimensions) => write!(
+                f,
+                "Weight field dimensions {}x{}x{} do not match the grid",
+                dimensions.width(),
+                dimensions.height(),
+                dimensions.depth()
+            ),
+            ProblemError::FixedOutOfBounds(point) => {
+                write!(f, "Fixed tile at {:?} lies outside of the grid", point)
+            }
+            ProblemError::UnknownPrefabTile(index) => {
+                write!(f, "Prefab {} has an unknown tile id", index)
+            }
+            ProblemError::PrefabUnplaceable(index) => {
+                write!(f, "Prefab {} does not fit anywhere in the grid", index)
+            }
+            ProblemError::PrefabOutOfBounds(index) => {
+                write!(f, "Prefab {} does not fit inside the grid at its position", index)
+            }
+            ProblemError::UnknownRewriteTile(index) => {
+                write!(f, "Rewrite rule {} replaces with an unknown tile", index)
+            }
+            ProblemError::UnknownDistributionTile(index) => {
+                write!(f, "Distribution target {} is not in the description", index)
+            }
+            ProblemError::SymmetryDimensions(dimensions) => write!(
+                f,
+                "A quarter turn symmetry needs a square grid, not {}x{}",
+                dimensions.width(),
+                dimensions.depth()
+            ),
+            ProblemError::PathOutOfBounds(point) => {
+                write!(f, "Path cell {:?} lies outside of the grid", point)
+            }
+            ProblemError::UnknownFixedTile(point) => {
+                write!(f, "Fixed tile at {:?} has an unknown tile id", point)
+            }
+            ProblemError::LayerDimensions(index) => {
+                write!(f, "Layer {} does not match the width and depth of the grid", index)
+            }
+            ProblemError::Layer(index, err) => write!(f, "Layer {}: {}", index, err),
+            ProblemError::Count(violation) => write!(
+                f,
+                "Count constraint {} ({}) needs between {} and {} tiles, but {} are placed and {} are possible",
+                violation.constraint,
+                violation.selector,
+                violation.min,
+                violation.max,
+                violation.assigned,
+                violation.possible
+            ),
+            ProblemError::AnchorOutOfBounds(point) => {
+                write!(f, "Connectivity anchor {:?} lies outside of the grid", point)
+            }
+            ProblemError::Disconnected(disconnection) => write!(
+                f,
+                "Connectivity constraint {} ({}) can no longer connect {:?} and {:?}",
+                disconnection.constraint,
+                disconnection.selector,
+                disconnection.from,
+                disconnection.to
+            ),
+        }
+    }
+}
+
+impl Display for Contradiction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No tile fits at {:?} during {}",
+            self.point,
+            match self.stage {
+                SolveStage::Placement => "placement rules",
+                SolveStage::Boundary => "boundary setup",
+                SolveStage::FixedTiles => "fixed tile seeding",
+                SolveStage::Prefabs => "prefab stamping",
+                SolveStage::GlobalConstraints => "count and connectivity constraints",
+                SolveStage::Collapse => "collapse",
+            }
+        )?;
+
+        if let Some((direction, neighbour)) = self.cause {
+            write!(
+                f,
+                ", constrained by {:?} neighbour {:?}",
+                direction, neighbour
+            )?;
         }
+
+        Ok(())
     }
 }
@@ -0,0 +1,255 @@
+use crate::{
+    description::{CompiledDescription, FixedTile, ProblemDescription, TileRotation},
+    error::{Contradiction, ProblemError},
+    solver::naive::{update_fixed_tiles, update_initial_sides, update_placement},
+    utils::{Direction, FieldGrid, Point},
+};
+use std::fmt::{Debug, Display};
+
+/// A small set of constraints that together can not be satisfied, with the adjacency rule
+/// where propagation ran out of tiles.
+#[derive(Debug, Clone)]
+pub struct Explanation<D> {
+    pub constraints: Vec<Constraint<D>>,
+    pub contradiction: Contradiction,
+    pub conflict: Option<AdjacencyConflict<D>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Constraint<D> {
+    /// Cells on the `direction` side of the grid have to connect to the `outside` tile,
+    /// which offers `sockets` to them.
+    Boundary {
+        direction: Direction,
+        outside: D,
+        sockets: Vec<String>,
+    },
+    Fixed(FixedTile<D>),
+}
+
+/// No tile left at `point` can connect to a tile left at `neighbour`.
+#[derive(Debug, Clone)]
+pub struct AdjacencyConflict<D> {
+    pub point: Point,
+    pub neighbour: Point,
+    pub direction: Direction,
+    /// The tiles that were still possible at `point`, with their sockets towards `neighbour`.
+    pub tiles: Vec<FaceSockets<D>>,
+    /// The tiles that are possible at `neighbour`, with their sockets towards `point`.
+    pub neighbour_tiles: Vec<FaceSockets<D>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FaceSockets<D> {
+    pub tile: D,
+    pub rotation: TileRotation,
+    pub sockets: Vec<String>,
+}
+
+impl<D: Clone + PartialEq + Debug> ProblemDescription<D> {
+    /// Looks for a minimal set of boundary sides and fixed tiles whose propagation alone
+    /// empties a cell. Returns `None` when propagation does not run into a contradiction,
+    /// in which case a failed solve was caused by the choices made during collapse.
+    ///
+    /// Adjacency rules are never part of the minimal set: they are assumed to hold, and only
+    /// the one rule where propagation ran out of tiles is reported as the `conflict`.
+    pub fn explain(&self) -> Option<Explanation<D>> {
+        if self.check().is_err() {
+            return None;
+        }
+
+        let compiled = self.compile_all();
+        let mut enabled = self.constraints(&compiled);
+
+        self.propagate(&compiled, &enabled).err()?;
+
+        // Deletion based minimisation: drop every constraint that is not needed for the conflict.
+        let mut index = 0;
+        while index < enabled.len() {
+            let without = enabled
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, c)| *c)
+                .collect::<Vec<_>>();
+
+            if self.propagate(&compiled, &without).is_err() {
+                enabled = without;
+            } else {
+                index += 1;
+            }
+        }
+
+        let (contradiction, grid) = *self.propagate(&compiled, &enabled).err()?;
+        let conflict = contradiction
+            .cause
+            .map(|(direction, neighbour)| AdjacencyConflict {
+                point: contradiction.point,
+                neighbour,
+                direction,
+                tiles: self.face_sockets(&compiled, &grid, contradiction.point, direction),
+                neighbour_tiles: self.face_sockets(
+                    &compiled,
+                    &grid,
+                    neighbour,
+                    direction.opposite(),
+                ),
+            });
+
+        Some(Explanation {
+            constraints: enabled.into_iter().map(|c| self.describe(c)).collect(),
+            contradiction,
+            conflict,
+        })
+    }
+
+    fn constraints(&self, compiled: &CompiledDescription) -> Vec<ExplainConstraint> {
+        Direction::ALL
+            .iter()
+            .filter(|d| compiled.boundary(**d).not_all())
+            .map(|d| ExplainConstraint::Boundary(*d))
+            .chain((0..self.fixed.len()).map(ExplainConstraint::Fixed))
+            .collect()
+    }
+
+    /// Propagates only the `enabled` constraints, returning the contradiction and the grid
+    /// at the moment it happened.
+    fn propagate(
+        &self,
+        compiled: &CompiledDescription,
+        enabled: &[ExplainConstraint],
+    ) -> Result<(), Box<(Contradiction, FieldGrid)>> {
+        let mut compiled = compiled.clone();
+        for direction in Direction::ALL {
+            if !enabled.contains(&ExplainConstraint::Boundary(direction)) {
+                compiled.boundary[direction.index()] = compiled.all_domain();
+            }
+        }
+        compiled.fixed = compiled
+            .fixed
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| enabled.contains(&ExplainConstraint::Fixed(*i)))
+            .map(|(_, fixed)| fixed.clone())
+            .collect();
+
+        let mut grid = compiled.grid();
+        let result = update_placement(&mut grid, &compiled)
+            .and_then(|_| update_initial_sides(&mut grid, &compiled))
+            .and_then(|_| update_fixed_tiles(&mut grid, &compiled));
+
+        match result {
+            Err(ProblemError::Contradiction(contradiction)) => Err(Box::new((contradiction, grid))),
+            _ => Ok(()),
+        }
+    }
+
+    fn face_sockets(
+        &self,
+        compiled: &CompiledDescription,
+        grid: &FieldGrid,
+        point: Point,
+        direction: Direction,
+    ) -> Vec<FaceSockets<D>> {
+        grid.get(point)
+            .map(|domain| {
+                domain
+                    .iter_ones()
+                    .map(|v| {
+                        let data = compiled.data(v);
+                        let tile = &self.tiles[data.tile_index];
+                        FaceSockets {
+                            tile: tile.id.clone(),
+                            rotation: data.rotation,
+                            sockets: tile
+                                .rotated_connections(direction, data.rotation)
+                                .into_iter()
+                                .map(String::from)
+                                .collect(),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn describe(&self, constraint: ExplainConstraint) -> Constraint<D> {
+        match constraint {
+            ExplainConstraint::Boundary(direction) => {
+                let outside = &self.tiles[0];
+                Constraint::Boundary {
+                    direction,
+                    outside: outside.id.clone(),
+                    sockets: outside
+                        .connections(direction.opposite())
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                }
+            }
+            ExplainConstraint::Fixed(index) => Constraint::Fixed(self.fixed[index].clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExplainConstraint {
+    Boundary(Direction),
+    Fixed(usize),
+}
+
+impl<D: Debug> Display for Explanation<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.contradiction)?;
+        for constraint in &self.constraints {
+            writeln!(f, "- {}", constraint)?;
+        }
+        if let Some(conflict) = &self.conflict {
+            write!(f, "- {}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Debug> Display for Constraint<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constraint::Boundary {
+                direction,
+                outside,
+                sockets,
+            } => write!(
+                f,
+                "The {:?} boundary {:?} forces every cell on that side to connect to one of {:?}",
+                direction, outside, sockets
+            ),
+            Constraint::Fixed(fixed) => write!(
+                f,
+                "Tile {:?} is fixed at {:?} with rotation {:?}",
+                fixed.id, fixed.point, fixed.rotation
+            ),
+        }
+    }
+}
+
+impl<D: Debug> Display for AdjacencyConflict<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "At {:?} the remaining tiles {} can not connect {:?} to {} at {:?}",
+            self.point,
+            format_faces(&self.tiles),
+            self.direction,
+            format_faces(&self.neighbour_tiles),
+            self.neighbour
+        )
+    }
+}
+
+fn format_faces<D: Debug>(faces: &[FaceSockets<D>]) -> String {
+    faces
+        .iter()
+        .map(|face| format!("{:?} ({:?}) {:?}", face.tile, face.rotation, face.sockets))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,175 @@
+use crate::{
+    description::{CompiledDescription, ProblemDescription, Tile, TileData},
+    error::ProblemError,
+    solver::generic,
+    topology::Topology,
+    utils::{Dimensions, Direction},
+};
+use bitvec::prelude::BitVec;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt::Debug};
+
+/// A directed edge from node `from` to node `to`. The label decides which faces of the two
+/// tiles have to connect.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub label: String,
+    pub to: usize,
+}
+
+impl GraphEdge {
+    pub fn new(from: usize, label: &str, to: usize) -> Self {
+        Self {
+            from,
+            label: label.to_string(),
+            to,
+        }
+    }
+}
+
+/// A problem over a graph instead of a grid. Tiles are compiled like those of a
+/// [`ProblemDescription`], every edge label maps to the direction in which `to` lies from
+/// `from`. Nothing lies outside of a graph, so there is no boundary.
+#[derive(Debug, Clone)]
+pub struct GraphDescription<D> {
+    pub connections: Vec<String>,
+    pub tiles: Vec<Tile<D>>,
+    pub nodes: usize,
+    pub edges: Vec<GraphEdge>,
+    pub labels: HashMap<String, Direction>,
+}
+
+impl<D> Default for GraphDescription<D> {
+    fn default() -> Self {
+        Self {
+            connections: Vec::new(),
+            tiles: Vec::new(),
+            nodes: 0,
+            edges: Vec::new(),
+            labels: HashMap::new(),
+        }
+    }
+}
+
+/// The nodes of a graph and, per node, its neighbours and the direction they lie in.
+/// Every edge is stored on both of its nodes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Graph {
+    neighbours: Vec<Vec<(Direction, usize)>>,
+}
+
+impl Graph {
+    pub fn new(nodes: usize) -> Self {
+        Self {
+            neighbours: vec![Vec::new(); nodes],
+        }
+    }
+
+    pub fn connect(&mut self, from: usize, direction: Direction, to: usize) {
+        self.neighbours[from].push((direction, to));
+        self.neighbours[to].push((direction.opposite(), from));
+    }
+}
+
+impl Topology for Graph {
+    type Cell = usize;
+    type Direction = Direction;
+
+    fn direction_index(direction: Direction) -> usize {
+        direction.index()
+    }
+
+    fn opposite(direction: Direction) -> Direction {
+        direction.opposite()
+    }
+
+    fn size(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    fn index(&self, cell: usize) -> usize {
+        cell
+    }
+
+    fn contains(&self, cell: usize) -> bool {
+        cell < self.neighbours.len()
+    }
+
+    fn cells(&self) -> Vec<usize> {
+        (0..self.neighbours.len()).collect()
+    }
+
+    fn neighbours(&self, cell: usize) -> impl Iterator<Item = (Direction, usize)> + '_ {
+        self.neighbours[cell].iter().copied()
+    }
+}
+
+impl<D: PartialEq + Clone + Debug> GraphDescription<D> {
+    /// Resolves the edge labels into a [`Graph`].
+    pub fn graph(&self) -> Result<Graph, ProblemError> {
+        let mut graph = Graph::new(self.nodes);
+        for edge in &self.edges {
+            if let Some(node) = [edge.from, edge.to].into_iter().find(|n| *n >= self.nodes) {
+                return Err(ProblemError::UnknownNode(node));
+            }
+            let direction = self
+                .labels
+                .get(&edge.label)
+                .ok_or_else(|| ProblemError::UnknownEdgeLabel(edge.label.clone()))?;
+            graph.connect(edge.from, *direction, edge.to);
+        }
+        Ok(graph)
+    }
+
+    /// Compiles the tiles into adjacency bitvecs per direction.
+    pub fn compile(&self) -> CompiledDescription {
+        ProblemDescription {
+            connections: self.connections.clone(),
+            dimensions: Dimensions::new(self.nodes, 1, 1),
+            tiles: self.tiles.clone(),
+            ..Default::default()
+        }
+        .compile_all()
+    }
+}
+
+pub struct GraphSolution<D> {
+    pub description: GraphDescription<D>,
+    pub compiled: CompiledDescription,
+    pub domains: Vec<BitVec>,
+}
+
+impl<D> GraphSolution<D> {
+    /// The collapsed variant at `node`, or `None` if the node is not in the graph.
+    pub fn tile_data(&self, node: usize) -> Option<&TileData> {
+        let index = self.domains.get(node)?.iter_ones().next()?;
+        Some(self.compiled.data(index))
+    }
+
+    pub fn tile(&self, node: usize) -> Option<&Tile<D>> {
+        self.tile_data(node)
+            .map(|data| &self.description.tiles[data.tile_index])
+    }
+}
+
+pub fn solve_graph<D: PartialEq + Clone + Debug, R: rand::Rng>(
+    rng: &mut R,
+    description: GraphDescription<D>,
+) -> Result<GraphSolution<D>, ProblemError> {
+    let graph = description.graph()?;
+    let compiled = description.compile();
+    if compiled.len() == 0 {
+        return Err(ProblemError::Unsatisfiable);
+    }
+
+    let mut domains = vec![compiled.all_domain(); graph.size()];
+    generic::collapse(rng, &graph, &compiled, &mut domains)
+        .map_err(|contradiction| ProblemError::GraphContradiction(contradiction.cell))?;
+
+    Ok(GraphSolution {
+        description,
+        compiled,
+        domains,
+    })
+}
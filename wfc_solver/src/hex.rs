@@ -0,0 +1,166 @@
+use crate::{
+    error::ProblemError,
+    solver::generic,
+    topology::{Adjacency, HexDirection, HexPoint, HexPrism, Topology},
+};
+use bitvec::prelude::BitVec;
+use serde::Deserialize;
+
+/// A tile of a hexagonal prism grid. Two sides connect when they share a socket.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HexTile<D> {
+    pub id: D,
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    /// Sockets of the six sides, in the order of [`HexDirection::SIDES`].
+    pub sides: [Vec<String>; 6],
+    pub can_rotate: bool,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone)]
+pub struct HexDescription<D> {
+    pub dimensions: HexPrism,
+    pub tiles: Vec<HexTile<D>>,
+}
+
+/// A tile turned by `rotation` sixths counter-clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexVariant {
+    pub tile_index: usize,
+    pub rotation: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HexRules {
+    pub variants: Vec<HexVariant>,
+    pub weights: Vec<f32>,
+    /// Per direction (in [`HexDirection::ALL`] order) and variant, the allowed neighbours.
+    pub adjacency: Vec<Vec<BitVec>>,
+}
+
+impl Adjacency for HexRules {
+    fn variants(&self) -> usize {
+        self.variants.len()
+    }
+
+    fn allowed(&self, direction: usize, variant: usize) -> &BitVec {
+        &self.adjacency[direction][variant]
+    }
+
+    fn weight(&self, variant: usize) -> f32 {
+        self.weights[variant]
+    }
+}
+
+impl<D> HexTile<D> {
+    /// Sockets facing `direction` once the tile is turned by `rotation`.
+    pub fn sockets(&self, direction: HexDirection, rotation: usize) -> &[String] {
+        match direction {
+            HexDirection::Up => &self.up,
+            HexDirection::Down => &self.down,
+            side => &self.sides[(side.index() + 6 - rotation % 6) % 6],
+        }
+    }
+}
+
+impl<D> HexDescription<D> {
+    pub fn compile(&self) -> HexRules {
+        let variants = self
+            .tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(tile_index, tile)| {
+                let rotations = if tile.can_rotate { 6 } else { 1 };
+                (0..rotations).map(move |rotation| HexVariant {
+                    tile_index,
+                    rotation,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let adjacency = HexDirection::ALL
+            .iter()
+            .map(|direction| {
+                variants
+                    .iter()
+                    .map(|from| {
+                        let sockets =
+                            self.tiles[from.tile_index].sockets(*direction, from.rotation);
+                        variants
+                            .iter()
+                            .map(|to| {
+                                self.tiles[to.tile_index]
+                                    .sockets(direction.opposite(), to.rotation)
+                                    .iter()
+                                    .any(|socket| sockets.contains(socket))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        HexRules {
+            weights: variants
+                .iter()
+                .map(|v| self.tiles[v.tile_index].weight)
+                .collect(),
+            variants,
+            adjacency,
+        }
+    }
+}
+
+pub struct HexSolution<D> {
+    pub description: HexDescription<D>,
+    pub rules: HexRules,
+    pub domains: Vec<BitVec>,
+}
+
+impl<D> HexSolution<D> {
+    /// The collapsed variant at `point`, or `None` if the point is outside the grid.
+    pub fn variant(&self, point: HexPoint) -> Option<HexVariant> {
+        if !self.description.dimensions.contains(point) {
+            return None;
+        }
+        let index = self.domains[self.description.dimensions.index(point)]
+            .iter_ones()
+            .next()?;
+        Some(self.rules.variants[index])
+    }
+
+    pub fn tile(&self, point: HexPoint) -> Option<&HexTile<D>> {
+        self.variant(point)
+            .map(|variant| &self.description.tiles[variant.tile_index])
+    }
+}
+
+/// Solves a hexagonal prism grid. Unlike [`crate::solve`] the outside of the grid places
+/// no restrictions on the cells.
+pub fn solve_hex<D, R: rand::Rng>(
+    rng: &mut R,
+    description: HexDescription<D>,
+) -> Result<HexSolution<D>, ProblemError> {
+    let rules = description.compile();
+    let topology = description.dimensions;
+    if topology.size() == 0 || rules.variants.is_empty() {
+        return Err(ProblemError::Unsatisfiable);
+    }
+
+    let mut domains = vec![BitVec::repeat(true, rules.variants.len()); topology.size()];
+    generic::collapse(rng, &topology, &rules, &mut domains).map_err(|contradiction| {
+        ProblemError::HexContradiction(contradiction.cell, contradiction.cause)
+    })?;
+
+    Ok(HexSolution {
+        description,
+        rules,
+        domains,
+    })
+}
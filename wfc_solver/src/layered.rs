@@ -0,0 +1,132 @@
+use crate::{
+    description::{ProblemDescription, Tile, TileData},
+    error::ProblemError,
+    solver::{naive::NaiveSolver, ProblemSolver},
+    utils::{Dimensions, Direction, Point},
+    Solution,
+};
+use bitvec::prelude::BitVec;
+use std::{collections::HashMap, fmt::Debug};
+
+/// A grid solved one storey at a time, from the bottom up. Every storey has its own
+/// description, e.g. shops on the ground floor and homes above.
+pub struct LayeredSolution<D> {
+    pub layers: Vec<Solution<D>>,
+}
+
+impl<D> LayeredSolution<D> {
+    /// The dimensions of all storeys stacked on top of each other.
+    pub fn dimensions(&self) -> Dimensions {
+        let first = self.layers[0].grid.dimensions();
+        let height = self
+            .layers
+            .iter()
+            .map(|layer| layer.grid.dimensions().height())
+            .sum();
+        Dimensions::new(first.width(), height, first.depth())
+    }
+
+    /// The storey `point` lies in, with the point inside of that storey.
+    pub fn layer(&self, point: Point) -> Option<(usize, Point)> {
+        let mut y = point.y();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let height = layer.grid.dimensions().height();
+            if y < height {
+                return Some((index, Point::new(point.x(), y, point.z())));
+            }
+            y -= height;
+        }
+        None
+    }
+
+    pub fn tile_data(&self, point: Point) -> Option<&TileData> {
+        let (index, point) = self.layer(point)?;
+        self.layers[index].tile_data(point)
+    }
+
+    pub fn tile(&self, point: Point) -> Option<&Tile<D>> {
+        let (index, point) = self.layer(point)?;
+        self.layers[index].tile(point)
+    }
+}
+
+/// Solves the storeys in order. The bottom cells of a storey only hold tiles that fit on
+/// the tiles below them, and the top cells of a storey only hold tiles that some tile of
+/// the next storey fits on. All storeys need the width and depth of the first.
+pub fn solve_layered<D: PartialEq + Debug, R: rand::Rng>(
+    rng: &mut R,
+    descriptions: Vec<ProblemDescription<D>>,
+) -> Result<LayeredSolution<D>, ProblemError> {
+    let mut layers: Vec<Solution<D>> = Vec::new();
+    let mut descriptions = descriptions.into_iter().enumerate().peekable();
+
+    while let Some((index, description)) = descriptions.next() {
+        let in_layer = |err| ProblemError::Layer(index, Box::new(err));
+        if let Some(first) = layers.first() {
+            let (a, b) = (first.grid.dimensions(), description.dimensions);
+            if (a.width(), a.depth()) != (b.width(), b.depth()) {
+                return Err(ProblemError::LayerDimensions(index));
+            }
+        }
+        description.check().map_err(in_layer)?;
+
+        let mut compiled = description.compile_all();
+        if let Some((_, above)) = descriptions.peek() {
+            compiled.boundary[Direction::Up.index()] = compiled
+                .transformation
+                .iter()
+                .map(|td| {
+                    above
+                        .tiles
+                        .iter()
+                        .any(|tile| fits_on(tile, &description.tiles[td.tile_index]))
+                })
+                .collect();
+        }
+        let (mut compiled, _) = compiled.prune();
+
+        if let Some(below) = layers.last() {
+            let dimensions = below.grid.dimensions();
+            let grid = compiled.grid();
+            for point in dimensions.points() {
+                if point.y() + 1 != dimensions.height() {
+                    continue;
+                }
+                let target = Point::new(point.x(), 0, point.z());
+                let tile = match below.tile(point) {
+                    Some(tile) if grid.is_active(target) => tile,
+                    _ => continue,
+                };
+                let domain: BitVec = compiled
+                    .transformation
+                    .iter()
+                    .map(|td| fits_on(&description.tiles[td.tile_index], tile))
+                    .collect();
+                compiled.fixed.push((target, domain));
+            }
+        }
+
+        let grid = NaiveSolver::default()
+            .solve(rng, &compiled)
+            .map_err(in_layer)?;
+        layers.push(Solution {
+            grid,
+            description,
+            compiled,
+            annotations: HashMap::new(),
+        });
+    }
+
+    if layers.is_empty() {
+        return Err(ProblemError::Unsatisfiable);
+    }
+    Ok(LayeredSolution { layers })
+}
+
+/// Whether `tile` can stand on `below`.
+fn fits_on<D>(tile: &Tile<D>, below: &Tile<D>) -> bool {
+    below
+        .up
+        .iter()
+        .any(|up| tile.down.iter().any(|down| up.is_connected(down)))
+}
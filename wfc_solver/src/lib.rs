@@ -1,24 +1,94 @@
-use description::{CompiledDescription, ProblemDescription};
+use description::{CompiledDescription, ProblemDescription, Tile, TileData, TileMetadata};
 use error::ProblemError;
 use solver::{naive::NaiveSolver, ProblemSolver};
-use utils::FieldGrid;
+use std::{collections::HashMap, fmt::Debug};
+use utils::{FieldGrid, Point};
 
 pub mod error;
 pub mod solver;
+pub mod topology;
 pub mod utils;
 
 pub mod description;
+pub mod description2d;
+pub mod explain;
+pub mod graph;
+pub mod hex;
+pub mod layered;
+pub mod rewrite;
+pub mod validation;
 
 pub struct Solution<D> {
     pub grid: FieldGrid,
     pub description: ProblemDescription<D>,
     pub compiled: CompiledDescription,
+    /// Metadata added to single cells, on top of the metadata of their tile.
+    pub annotations: HashMap<Point, TileMetadata>,
 }
 
-pub fn solve<D, R: rand::Rng>(
+impl<D> Solution<D> {
+    /// The collapsed variant at `point`, or `None` if the point is outside the grid or
+    /// masked out.
+    pub fn tile_data(&self, point: Point) -> Option<&TileData> {
+        if !self.grid.is_active(point) {
+            return None;
+        }
+        let index = self.grid.get(point)?.iter_ones().next()?;
+        Some(self.compiled.data(index))
+    }
+
+    pub fn tile(&self, point: Point) -> Option<&Tile<D>> {
+        self.tile_data(point)
+            .map(|data| &self.description.tiles[data.tile_index])
+    }
+
+    pub fn metadata(&self, point: Point) -> Option<&TileMetadata> {
+        self.tile(point).map(|tile| &tile.metadata)
+    }
+
+    /// The metadata of the tile at `point` with the annotations of the cell added.
+    pub fn cell_metadata(&self, point: Point) -> Option<TileMetadata> {
+        let mut metadata = self.metadata(point)?.clone();
+        if let Some(annotations) = self.annotations.get(&point) {
+            metadata.extend(annotations.clone());
+        }
+        Some(metadata)
+    }
+
+    /// The share of the placed tiles that every tile of the description makes up, in the
+    /// order of the tiles. A multi-cell tile counts once, like in [`Solution::placements`].
+    pub fn histogram(&self) -> Vec<(&D, f32)> {
+        let mut counts = vec![0usize; self.description.tiles.len()];
+        let mut placed = 0;
+        for (_, data) in self.placements() {
+            counts[data.tile_index] += 1;
+            placed += 1;
+        }
+
+        self.description
+            .tiles
+            .iter()
+            .zip(counts)
+            .map(|(tile, count)| (&tile.id, count as f32 / placed.max(1) as f32))
+            .collect()
+    }
+
+    /// Every placed tile once, at the cell it is anchored in. The other cells of a
+    /// multi-cell tile are left out.
+    pub fn placements(&self) -> impl Iterator<Item = (Point, &TileData)> + '_ {
+        self.grid
+            .points()
+            .filter_map(|point| Some((point, self.tile_data(point)?)))
+            .filter(|(_, data)| data.is_anchor())
+    }
+}
+
+pub fn solve<D: PartialEq + Debug, R: rand::Rng>(
     rng: &mut R,
     description: ProblemDescription<D>,
 ) -> Result<Solution<D>, ProblemError> {
+    description.check()?;
+
     let compiled = description.compile();
 
     println!("Compiled");
@@ -64,5 +134,6 @@ pub fn solve<D, R: rand::Rng>(
         grid,
         description,
         compiled,
+        annotations: HashMap::new(),
     })
 }
@@ -0,0 +1,131 @@
+use crate::{
+    description::{Placement, TileMetadata, TileRotation, TileSelector},
+    error::ProblemError,
+    utils::{Direction, Point},
+    Solution,
+};
+use bitvec::prelude::BitVec;
+use serde::Deserialize;
+
+/// Replaces or annotates solved tiles that match a small neighbourhood pattern, e.g. a block
+/// with air on its backward side on the ground floor.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RewriteRule<D> {
+    pub tile: TileSelector<D>,
+    /// Tiles that have to lie next to the matched tile. The directions are sides of the tile,
+    /// so they turn with it.
+    #[serde(default)]
+    pub neighbours: Vec<(Direction, TileSelector<D>)>,
+    /// Where the matched tile has to lie.
+    #[serde(default)]
+    pub placement: Placement,
+    /// The tile that takes its place, in the same rotation and flip. Usually a decorative
+    /// tile, whose variants are never pruned.
+    #[serde(default)]
+    pub replace: Option<D>,
+    /// Added to the annotations of the cell.
+    #[serde(default)]
+    pub metadata: TileMetadata,
+}
+
+impl<D> RewriteRule<D> {
+    /// A rule that matches `tile` anywhere and changes nothing yet.
+    pub fn new(tile: TileSelector<D>) -> Self {
+        Self {
+            tile,
+            neighbours: Vec::new(),
+            placement: Placement::default(),
+            replace: None,
+            metadata: TileMetadata::new(),
+        }
+    }
+}
+
+/// The direction a side of a tile faces once the tile is rotated.
+fn world_side(side: Direction, rotation: TileRotation) -> Direction {
+    let turns = match rotation {
+        TileRotation::R0 => 0,
+        TileRotation::R90 => 1,
+        TileRotation::R180 => 2,
+        TileRotation::R270 => 3,
+    };
+    let ring = [
+        Direction::Forward,
+        Direction::Right,
+        Direction::Backward,
+        Direction::Left,
+    ];
+    match ring.iter().position(|d| *d == side) {
+        Some(index) => ring[(index + turns) % 4],
+        None => side,
+    }
+}
+
+impl<D: PartialEq> Solution<D> {
+    /// Runs the rules in order over the solved grid, every rule sees the changes of the rules
+    /// before it. Returns the number of cells that were replaced or annotated. On an error the
+    /// solution is left as it was before the first rule.
+    pub fn rewrite(&mut self, rules: &[RewriteRule<D>]) -> Result<usize, ProblemError> {
+        let grid = self.grid.clone();
+        let annotations = self.annotations.clone();
+        let result = self.apply_rules(rules);
+        if result.is_err() {
+            self.grid = grid;
+            self.annotations = annotations;
+        }
+        result
+    }
+
+    fn apply_rules(&mut self, rules: &[RewriteRule<D>]) -> Result<usize, ProblemError> {
+        let mut rewritten = 0;
+        for (index, rule) in rules.iter().enumerate() {
+            let matched = self
+                .grid
+                .points()
+                .filter(|point| self.matches(rule, *point))
+                .collect::<Vec<_>>();
+
+            for point in matched {
+                if let Some(id) = &rule.replace {
+                    let data = self.tile_data(point).unwrap().clone();
+                    let variant = (0..self.compiled.len())
+                        .find(|i| {
+                            let other = self.compiled.data(*i);
+                            self.description.tiles[other.tile_index].id == *id
+                                && other.rotation == data.rotation
+                                && other.flipped == data.flipped
+                                && other.part == data.part
+                        })
+                        .ok_or(ProblemError::UnknownRewriteTile(index))?;
+
+                    let mut domain = BitVec::repeat(false, self.compiled.len());
+                    domain.set(variant, true);
+                    self.grid.set(point, domain);
+                }
+                self.annotations
+                    .entry(point)
+                    .or_default()
+                    .extend(rule.metadata.clone());
+                rewritten += 1;
+            }
+        }
+        Ok(rewritten)
+    }
+
+    fn matches(&self, rule: &RewriteRule<D>, point: Point) -> bool {
+        let dimensions = self.grid.dimensions();
+        let (data, tile) = match (self.tile_data(point), self.tile(point)) {
+            (Some(data), Some(tile)) => (data, tile),
+            _ => return false,
+        };
+
+        rule.tile.matches(tile)
+            && rule.placement.allows(point, dimensions)
+            && rule.neighbours.iter().all(|(side, selector)| {
+                let neighbour = point
+                    .neighbour(world_side(*side, data.rotation), dimensions)
+                    .and_then(|neighbour| self.tile(neighbour));
+                matches!(neighbour, Some(tile) if selector.matches(tile))
+            })
+    }
+}
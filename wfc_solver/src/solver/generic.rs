@@ -0,0 +1,133 @@
+use crate::topology::{Adjacency, Topology};
+use bitvec::prelude::BitVec;
+use rand::{prelude::IteratorRandom, Rng};
+use std::collections::VecDeque;
+
+/// A cell of topology `T` ran out of variants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyContradiction<T: Topology> {
+    pub cell: T::Cell,
+    /// The direction of, and the neighbour that removed the last options of `cell`.
+    pub cause: Option<(T::Direction, T::Cell)>,
+}
+
+/// Restricts `cell` to `domain` and propagates the change through the topology. A
+/// contradicting cell keeps its last non-empty domain.
+pub fn propagate<T: Topology, A: Adjacency>(
+    topology: &T,
+    rules: &A,
+    domains: &mut [BitVec],
+    cell: T::Cell,
+    domain: BitVec,
+) -> Result<(), TopologyContradiction<T>> {
+    propagate_with(topology, rules, domains, cell, domain, |_| {})
+}
+
+/// Like [`propagate`], calling `changed` with every cell whose domain shrank.
+pub fn propagate_with<T: Topology, A: Adjacency, F: FnMut(T::Cell)>(
+    topology: &T,
+    rules: &A,
+    domains: &mut [BitVec],
+    cell: T::Cell,
+    domain: BitVec,
+    mut changed: F,
+) -> Result<(), TopologyContradiction<T>> {
+    let mut queue = VecDeque::new();
+    queue.push_back((cell, domain, None));
+
+    while let Some((cell, new_domain, cause)) = queue.pop_front() {
+        if !topology.contains(cell) {
+            continue;
+        }
+
+        let current = &mut domains[topology.index(cell)];
+        let updated = current.clone() & new_domain;
+        if updated == *current {
+            continue;
+        }
+        if updated.not_any() {
+            return Err(TopologyContradiction { cell, cause });
+        }
+        *current = updated.clone();
+        changed(cell);
+
+        for (direction, neighbour) in topology.neighbours(cell) {
+            let index = T::direction_index(direction);
+            let potential = updated
+                .iter_ones()
+                .fold(BitVec::repeat(false, updated.len()), |acc, v| {
+                    acc | rules.allowed(index, v).clone()
+                });
+
+            let neighbour_domain = &domains[topology.index(neighbour)];
+            if neighbour_domain.clone() & potential.clone() != *neighbour_domain {
+                queue.push_back((neighbour, potential, Some((T::opposite(direction), cell))));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Picks a variant of `domain` with a chance proportional to its weight.
+pub fn choose_weighted<A: Adjacency, R: Rng>(
+    rng: &mut R,
+    domain: &BitVec,
+    rules: &A,
+) -> Option<usize> {
+    choose_weighted_by(rng, domain, |i| rules.weight(i))
+}
+
+/// Like [`choose_weighted`], with the weight of each variant given by `weight`.
+pub fn choose_weighted_by<R: Rng, F: Fn(usize) -> f32>(
+    rng: &mut R,
+    domain: &BitVec,
+    weight: F,
+) -> Option<usize> {
+    let total: f32 = domain.iter_ones().map(&weight).sum();
+    if total <= 0.0 {
+        return domain.iter_ones().choose(rng);
+    }
+
+    let mut pick = rng.gen_range(0.0..total);
+    for i in domain.iter_ones() {
+        let weight = weight(i);
+        if pick < weight {
+            return Some(i);
+        }
+        pick -= weight;
+    }
+
+    domain.iter_ones().last()
+}
+
+/// Collapses every cell of the topology, always picking the cell with the fewest
+/// remaining variants.
+pub fn collapse<T: Topology, A: Adjacency, R: Rng>(
+    rng: &mut R,
+    topology: &T,
+    rules: &A,
+    domains: &mut [BitVec],
+) -> Result<(), TopologyContradiction<T>> {
+    let mut cells = topology.cells();
+
+    loop {
+        cells.retain(|cell| domains[topology.index(*cell)].count_ones() > 1);
+
+        let cell = match cells
+            .iter()
+            .min_by_key(|cell| domains[topology.index(**cell)].count_ones())
+        {
+            Some(cell) => *cell,
+            None => return Ok(()),
+        };
+
+        let domain = &domains[topology.index(cell)];
+        let variant = choose_weighted(rng, domain, rules)
+            .ok_or(TopologyContradiction { cell, cause: None })?;
+
+        let mut chosen = BitVec::repeat(false, domain.len());
+        chosen.set(variant, true);
+        propagate(topology, rules, domains, cell, chosen)?;
+    }
+}
@@ -2,6 +2,7 @@ use rand::Rng;
 
 use crate::{description::CompiledDescription, error::ProblemError, Solution, utils::FieldGrid};
 
+pub mod generic;
 pub mod naive;
 
 pub trait ProblemSolver: Default {
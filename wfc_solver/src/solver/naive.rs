@@ -1,11 +1,11 @@
-use super::ProblemSolver;
+use super::{generic, ProblemSolver};
 use crate::{
     description::CompiledDescription,
-    error::ProblemError,
-    utils::{FieldGrid, Point},
+    error::{Contradiction, CountViolation, Disconnection, ProblemError, SolveStage},
+    utils::{Dimensions, Direction, FieldGrid, Point},
 };
 use bitvec::prelude::BitVec;
-use rand::{prelude::IteratorRandom, Rng};
+use rand::{seq::SliceRandom, Rng};
 use std::collections::VecDeque;
 
 #[derive(Debug, Default)]
@@ -17,21 +17,29 @@ impl ProblemSolver for NaiveSolver {
         rng: &mut R,
         description: &CompiledDescription,
     ) -> Result<FieldGrid, ProblemError> {
-        // if description.dimensions() != description.initial_grid().dimensions() {
-        //     return Err(ProblemError::Dimensions);
-        // }
+        if description.dimensions().len() == 0 {
+            return Err(ProblemError::Dimensions(description.dimensions()));
+        }
+        if description.len() == 0 {
+            return Err(ProblemError::Unsatisfiable);
+        }
 
-        let mut grid = FieldGrid::new(description.dimensions(), description.all_domain());
+        let mut grid = description.grid();
 
-        // update_initial_domain(&mut grid, description.initial_grid())?;
-        update_initial_sides(&mut grid, description)?;
+        update_placement(&mut grid, description)
+            .map_err(|e| e.in_stage(SolveStage::Placement))?;
+        update_initial_sides(&mut grid, description)
+            .map_err(|e| e.in_stage(SolveStage::Boundary))?;
+        update_fixed_tiles(&mut grid, description)
+            .map_err(|e| e.in_stage(SolveStage::FixedTiles))?;
+        update_prefabs(&mut grid, rng, description)
+            .map_err(|e| e.in_stage(SolveStage::Prefabs))?;
+        track_constraints(&mut grid, description);
+        update_symmetry(&mut grid, description)
+            .and_then(|_| update_global_constraints(&mut grid, description))
+            .map_err(|e| e.in_stage(SolveStage::GlobalConstraints))?;
         print_dimensions(&grid);
 
-        if !grid.is_satisfiable() {
-            // panic!("Error: It went wrong with initial sides");
-            return Err(ProblemError::Unsatisfiable);
-        }
-
         start_processs(&mut grid, rng, description)?;
 
         Ok(grid)
@@ -55,22 +63,84 @@ fn print_dimensions(grid: &FieldGrid) {
     }
 }
 
-fn update_initial_domain(
+/// Restricts every cell to the variants whose placement rules allow them there.
+pub fn update_placement(
+    grid: &mut FieldGrid,
+    description: &CompiledDescription,
+) -> Result<(), ProblemError> {
+    for point in grid.points().collect::<Vec<_>>() {
+        let allowed = description.placement(point);
+        if grid.should_update(point, &allowed) {
+            propagate_point(grid, point, allowed, description)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn update_fixed_tiles(
+    grid: &mut FieldGrid,
+    description: &CompiledDescription,
+) -> Result<(), ProblemError> {
+    for (point, domain) in &description.fixed {
+        propagate_point(grid, *point, domain.clone(), description)?;
+    }
+
+    Ok(())
+}
+
+/// Stamps every prefab at a random position and rotation among those where it fits.
+pub fn update_prefabs<R: Rng>(
     grid: &mut FieldGrid,
-    initial_grid: &FieldGrid,
+    rng: &mut R,
+    description: &CompiledDescription,
 ) -> Result<(), ProblemError> {
-    todo!("Update and propage tiles");
+    let dimensions = grid.dimensions();
+    for (index, prefab) in description.prefabs.iter().enumerate() {
+        let mut candidates = Vec::new();
+        for (size, cells) in &prefab.rotations {
+            let origins = match prefab.at {
+                Some(at) => vec![at],
+                None => dimensions.points().collect(),
+            };
+            for origin in origins {
+                let offset = |p: Point| {
+                    Point::new(origin.x() + p.x(), origin.y() + p.y(), origin.z() + p.z())
+                };
+                if size.points().all(|p| grid.is_active(offset(p))) {
+                    candidates.push(
+                        cells
+                            .iter()
+                            .map(|(p, domain)| (offset(*p), domain))
+                            .collect::<Vec<_>>(),
+                    );
+                }
+            }
+        }
+        candidates.shuffle(rng);
+
+        let placed = candidates.into_iter().find_map(|cells| {
+            let mut trial = grid.clone();
+            cells
+                .into_iter()
+                .try_for_each(|(point, domain)| {
+                    propagate_point(&mut trial, point, domain.clone(), description)
+                })
+                .ok()
+                .map(|_| trial)
+        });
+        *grid = placed.ok_or(ProblemError::PrefabUnplaceable(index))?;
+    }
+
+    Ok(())
 }
 
 macro_rules! rekt {
     ($description:ident, $($dir:ident),*) => {{
-        let mut vec = BitVec::repeat(false, $description.len());
-        $description.all_domain()
-        .iter_ones()
+        let mut vec = $description.all_domain();
         $(
-            .filter(|x| $description.$dir(*x)[0])
+            vec &= $description.boundary(Direction::$dir);
         )*
-        .for_each(|x| vec.set(x, true));
         vec
     }};
 
@@ -91,24 +161,41 @@ pub fn update_initial_sides(
     grid: &mut FieldGrid,
     description: &CompiledDescription,
 ) -> Result<(), ProblemError> {
+    if grid.dimensions().len() == 0 {
+        return Err(ProblemError::Dimensions(grid.dimensions()));
+    }
+
     let width = dbg!(0..grid.dimensions().width());
     let height = dbg!(0..grid.dimensions().height());
     let depth = dbg!(0..grid.dimensions().depth());
 
     let min_x = 0..1;
-    // let min_y = 0..1;
+    let min_y = 0..1;
     let min_z = 0..1;
     let max_x = (grid.dimensions().width() - 1)..grid.dimensions().width();
     let max_y = (grid.dimensions().height() - 1)..grid.dimensions().height();
     let max_z = (grid.dimensions().depth() - 1)..grid.dimensions().depth();
 
-    // // rekt!(range grid, width, min_y, depth, description, down);
+    rekt!(range grid, width, min_y, depth, description, Down);
+    rekt!(range grid, width, max_y, depth, description, Up);
+    rekt!(range grid, min_x, height, depth, description, Left);
+    rekt!(range grid, max_x, height, depth, description, Right);
+    rekt!(range grid, width, height, max_z, description, Forward);
+    rekt!(range grid, width, height, min_z, description, Backward);
 
-    rekt!(range grid, width, max_y, depth, description, up);
-    rekt!(range grid, min_x, height, depth, description, left);
-    rekt!(range grid, max_x, height, depth, description, right);
-    rekt!(range grid, width, height, max_z, description, forward);
-    rekt!(range grid, width, height, min_z, description, backward);
+    if grid.mask().is_some() {
+        let dimensions = grid.dimensions();
+        for point in grid.points().collect::<Vec<_>>() {
+            for direction in Direction::ALL {
+                if let Some(neighbour) = point.neighbour(direction, dimensions) {
+                    if !grid.is_active(neighbour) {
+                        let domain = description.mask_boundary(direction).clone();
+                        propagate_point(grid, point, domain, description)?;
+                    }
+                }
+            }
+        }
+    }
 
     Ok(())
 }
@@ -118,14 +205,7 @@ fn start_processs<R: rand::Rng>(
     rng: &mut R,
     description: &CompiledDescription,
 ) -> Result<(), ProblemError> {
-    let mut points = Vec::with_capacity(grid.dimensions().len());
-    for x in 0..grid.dimensions().width() {
-        for y in 0..grid.dimensions().height() {
-            for z in 0..grid.dimensions().depth() {
-                points.push(Point::new(x, y, z));
-            }
-        }
-    }
+    let mut points = grid.points().collect::<Vec<_>>();
 
     while !grid.is_complete() {
         points = points
@@ -137,83 +217,312 @@ fn start_processs<R: rand::Rng>(
             .iter()
             .min_by_key(|x| grid.get(**x).unwrap().count_ones())
         {
+            let factors = description
+                .distribution
+                .as_ref()
+                .map(|distribution| distribution.factors(grid, points.len()));
             let point_vec = grid.get(*point).unwrap();
 
-            let fixed_index = point_vec
-                .iter_ones()
-                .choose(rng)
-                .ok_or(ProblemError::Unsatisfiable)?;
+            let fixed_index = generic::choose_weighted_by(rng, point_vec, |variant| {
+                let factor = factors.as_ref().map_or(1.0, |factors| factors[variant]);
+                description.weight_at(variant, *point) * factor
+            })
+            .ok_or(ProblemError::Contradiction(Contradiction {
+                point: *point,
+                stage: SolveStage::Collapse,
+                cause: None,
+            }))?;
 
             let mut vec = BitVec::repeat(false, point_vec.len());
             vec.set(fixed_index, true);
 
             propagate_point(grid, *point, vec, description)?;
+            update_global_constraints(grid, description)?;
         }
     }
 
     Ok(())
 }
 
-macro_rules! handle_direction {
-    ($point:ident, $dimensions:ident, $domain:ident, $description:ident, $grid:ident, $queue:ident, $($direction:ident),+ ) => {{
-        $(
-            handle_direction!(dir $point, $dimensions, $domain, $description, $grid, $queue, $direction);
-        )+
-    }};
-    (dir $point:ident, $dimensions:ident, $domain:ident, $description:ident, $grid:ident, $queue:ident, $direction:ident) => {{
-        if let Some(neighbour_point) = $point.$direction($dimensions) {
-            let potential_domain = (potential_domain((&$domain), |i| $description.$direction(i).clone()));
-            if $grid.should_update(neighbour_point, &potential_domain) {
-                $queue.push_back((neighbour_point, potential_domain));
+/// Tracks the variants of every count constraint, then those of every connectivity
+/// constraint. Count `i` reads tracked set `i`, connectivity constraint `i` reads tracked set
+/// `counts.len() + i`.
+pub fn track_constraints(grid: &mut FieldGrid, description: &CompiledDescription) {
+    for count in &description.counts {
+        grid.track(count.variants.clone());
+    }
+    for connectivity in &description.connectivity {
+        grid.track(connectivity.variants.clone());
+    }
+}
+
+/// Enforces the count constraints: once a tile reaches its maximum it is removed from every
+/// other cell, and once every remaining candidate cell is needed for the minimum they are
+/// forced to it. Repeats until nothing changes.
+pub fn update_counts(
+    grid: &mut FieldGrid,
+    description: &CompiledDescription,
+) -> Result<(), ProblemError> {
+    loop {
+        let mut changed = false;
+
+        for (index, count) in description.counts.iter().enumerate() {
+            let tracked = grid.tracked(index);
+            let assigned = tracked.definite.count_ones();
+            let possible = tracked.possible.count_ones();
+            if assigned > count.max || possible < count.min {
+                return Err(ProblemError::Count(CountViolation {
+                    constraint: index,
+                    selector: count.selector.clone(),
+                    assigned,
+                    possible,
+                    min: count.min,
+                    max: count.max,
+                }));
+            }
+
+            let restriction = if assigned == count.max {
+                !count.variants.clone()
+            } else if possible == count.min {
+                count.variants.clone()
+            } else {
+                continue;
+            };
+
+            let open = tracked.possible.clone() & !tracked.definite.clone();
+            for id in open.iter_ones() {
+                let point = Point::from_id(id, grid.dimensions());
+                propagate_point(grid, point, restriction.clone(), description)?;
+                changed = true;
             }
         }
-    }};
+
+        if !changed {
+            return Ok(());
+        }
+    }
 }
+
+/// Runs the count and connectivity constraints until neither restricts the grid any further.
+pub fn update_global_constraints(
+    grid: &mut FieldGrid,
+    description: &CompiledDescription,
+) -> Result<(), ProblemError> {
+    loop {
+        update_counts(grid, description)?;
+        if !update_connectivity(grid, description)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Ties every cell to its image under each symmetry. Only needed once: afterwards
+/// [`propagate_point`] keeps the ties of every cell whose image changes.
+pub fn update_symmetry(
+    grid: &mut FieldGrid,
+    description: &CompiledDescription,
+) -> Result<(), ProblemError> {
+    for symmetry in 0..description.symmetry.len() {
+        for point in grid.points().collect::<Vec<_>>() {
+            if let Some(allowed) = symmetry_domain(grid, description, symmetry, point) {
+                if grid.should_update(point, &allowed) {
+                    propagate_point(grid, point, allowed, description)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The variants `point` may keep under symmetry `index`: those whose image is possible in the
+/// image cell, also when that is the cell itself or a neighbour of it. `None` when either cell
+/// is not part of the grid.
+fn symmetry_domain(
+    grid: &FieldGrid,
+    description: &CompiledDescription,
+    index: usize,
+    point: Point,
+) -> Option<BitVec> {
+    let symmetry = &description.symmetry[index];
+    let dimensions = grid.dimensions();
+    let image = symmetry.symmetry.image(point, dimensions);
+    if !grid.is_active(point) || !grid.is_active(image) {
+        return None;
+    }
+    let side = Direction::ALL
+        .into_iter()
+        .find(|d| point.neighbour(*d, dimensions) == Some(image));
+
+    let targets = grid.get(image)?;
+    let allowed = (0..description.len())
+        .map(|variant| match symmetry.images[variant] {
+            Some(other) if image == point => other == variant,
+            Some(other) => {
+                targets[other]
+                    && match side {
+                        Some(d) => description.direction(d, variant)[other],
+                        None => true,
+                    }
+            }
+            None => false,
+        })
+        .collect();
+    Some(allowed)
+}
+
+/// Enforces the connectivity constraints. Cells that certainly hold a matching tile have to be
+/// reachable from each other through cells that still could. Candidates that can not reach them
+/// lose the matching tiles, and candidates every path depends on are forced to them.
+/// Returns whether the grid changed.
+pub fn update_connectivity(
+    grid: &mut FieldGrid,
+    description: &CompiledDescription,
+) -> Result<bool, ProblemError> {
+    let dimensions = grid.dimensions();
+    let mut changed = false;
+
+    for (index, connectivity) in description.connectivity.iter().enumerate() {
+        for anchor in &connectivity.anchors {
+            if grid.should_update(*anchor, &connectivity.variants) {
+                propagate_point(grid, *anchor, connectivity.variants.clone(), description)?;
+                changed = true;
+            }
+        }
+
+        let tracked = grid.tracked(description.counts.len() + index);
+        let (possible, definite) = (tracked.possible.clone(), tracked.definite.clone());
+        let start = match definite.first_one() {
+            Some(start) => Point::from_id(start, dimensions),
+            None => continue,
+        };
+
+        let (region, cuts) = cut_cells(dimensions, &possible, &definite, start);
+        if let Some(other) = definite.iter_ones().find(|id| !region[*id]) {
+            return Err(ProblemError::Disconnected(Disconnection {
+                constraint: index,
+                selector: connectivity.selector.clone(),
+                from: start,
+                to: Point::from_id(other, dimensions),
+            }));
+        }
+
+        for id in (possible.clone() & !definite.clone()).iter_ones() {
+            if !region[id] {
+                let point = Point::from_id(id, dimensions);
+                propagate_point(grid, point, !connectivity.variants.clone(), description)?;
+                changed = true;
+            }
+        }
+        for id in (cuts & !definite).iter_ones() {
+            let point = Point::from_id(id, dimensions);
+            propagate_point(grid, point, connectivity.variants.clone(), description)?;
+            changed = true;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Walks the `open` cells reachable from `start` depth first, returning the reached cells and
+/// the cells that separate `start` from some `marked` cell. Those are the articulation points
+/// with a subtree below them that holds a marked cell.
+fn cut_cells(
+    dimensions: Dimensions,
+    open: &BitVec,
+    marked: &BitVec,
+    start: Point,
+) -> (BitVec, BitVec) {
+    let mut order = vec![0; dimensions.len()];
+    let mut low = vec![0; dimensions.len()];
+    let mut below = vec![0; dimensions.len()];
+    let mut cuts = BitVec::repeat(false, dimensions.len());
+
+    let id = start.id(dimensions);
+    order[id] = 1;
+    low[id] = 1;
+    below[id] = marked[id] as usize;
+    let mut visited = 1;
+    let mut stack = vec![(start, 0)];
+
+    while let Some((point, next)) = stack.last().copied() {
+        let id = point.id(dimensions);
+        if let Some(direction) = Direction::ALL.get(next) {
+            stack.last_mut().unwrap().1 += 1;
+            let neighbour = match point.neighbour(*direction, dimensions) {
+                Some(neighbour) if open[neighbour.id(dimensions)] => neighbour,
+                _ => continue,
+            };
+            let other = neighbour.id(dimensions);
+            if order[other] == 0 {
+                visited += 1;
+                order[other] = visited;
+                low[other] = visited;
+                below[other] = marked[other] as usize;
+                stack.push((neighbour, 0));
+            } else {
+                low[id] = low[id].min(order[other]);
+            }
+            continue;
+        }
+
+        stack.pop();
+        if let Some((parent, _)) = stack.last() {
+            let parent = parent.id(dimensions);
+            low[parent] = low[parent].min(low[id]);
+            below[parent] += below[id];
+            if low[id] >= order[parent] && below[id] > 0 {
+                cuts.set(parent, true);
+            }
+        }
+    }
+
+    let reached = order.iter().map(|o| *o != 0).collect();
+    (reached, cuts)
+}
+
+/// Restricts `point` to `vec` and propagates the change through the adjacency rules and the
+/// symmetry ties.
 pub fn propagate_point(
     grid: &mut FieldGrid,
     point: Point,
     vec: BitVec,
     description: &CompiledDescription,
 ) -> Result<(), ProblemError> {
+    let dimensions = grid.dimensions();
     let mut queue = VecDeque::new();
     queue.push_back((point, vec));
 
-    while let Some((point, new_domain)) = queue.pop_front() {
-        if let Some(updated) = grid.update(point, &new_domain) {
-            if updated.count_ones() == 0 {
-                return Err(ProblemError::Unsatisfiable);
-            }
-            let domain = updated.clone();
-            let dimensions = grid.dimensions();
+    while let Some((point, vec)) = queue.pop_front() {
+        let mut changed = Vec::new();
+        let (topology, domains) = grid.split_mut();
+        let result = generic::propagate_with(&topology, description, domains, point, vec, |cell| {
+            changed.push(cell)
+        })
+        .map_err(|contradiction| {
+            ProblemError::Contradiction(Contradiction {
+                point: contradiction.cell,
+                stage: SolveStage::Collapse,
+                cause: contradiction.cause,
+            })
+        });
 
-            handle_direction!(
-                point,
-                dimensions,
-                domain,
-                description,
-                grid,
-                queue,
-                up,
-                down,
-                backward,
-                forward,
-                left,
-                right
-            );
+        for cell in &changed {
+            grid.refresh(*cell);
         }
-    }
+        result?;
 
-    // println!("Propagated: {:?} -- {}", point, propagated);
+        for cell in changed {
+            for (index, symmetry) in description.symmetry.iter().enumerate() {
+                let tied = symmetry.symmetry.preimage(cell, dimensions);
+                if let Some(allowed) = symmetry_domain(grid, description, index, tied) {
+                    if grid.should_update(tied, &allowed) {
+                        queue.push_back((tied, allowed));
+                    }
+                }
+            }
+        }
+    }
 
     Ok(())
 }
-
-fn potential_domain<F>(domain: &BitVec, factory: F) -> BitVec
-where
-    F: Fn(usize) -> BitVec,
-{
-    domain
-        .iter_ones()
-        .map(|i| factory(i))
-        .fold(BitVec::repeat(false, domain.len()), |acc, x| acc | x)
-}
@@ -0,0 +1,251 @@
+use crate::utils::{Dimensions, Direction, Mask, Point};
+use bitvec::prelude::BitVec;
+use std::fmt::Debug;
+
+/// The shape of a grid or graph: its cells, the directions between them and which cells
+/// lie in which direction of another.
+pub trait Topology {
+    type Cell: Copy + PartialEq + Debug;
+    type Direction: Copy + PartialEq + Debug;
+
+    /// Used to look up the adjacency rules of `direction`.
+    fn direction_index(direction: Self::Direction) -> usize;
+    fn opposite(direction: Self::Direction) -> Self::Direction;
+
+    /// Number of cell slots, including inactive ones.
+    fn size(&self) -> usize;
+    fn index(&self, cell: Self::Cell) -> usize;
+    fn contains(&self, cell: Self::Cell) -> bool;
+    /// The active cells.
+    fn cells(&self) -> Vec<Self::Cell>;
+    /// The active cells next to `cell`, with the direction they lie in.
+    fn neighbours(
+        &self,
+        cell: Self::Cell,
+    ) -> impl Iterator<Item = (Self::Direction, Self::Cell)> + '_;
+}
+
+/// Adjacency rules between variants, indexed by the direction index of a [`Topology`].
+pub trait Adjacency {
+    fn variants(&self) -> usize;
+    /// The variants that may lie in direction `direction` of `variant`.
+    fn allowed(&self, direction: usize, variant: usize) -> &BitVec;
+    fn weight(&self, variant: usize) -> f32;
+}
+
+/// A box of cubes with six neighbours each, optionally restricted to the active cells of a mask.
+#[derive(Debug, Clone, Copy)]
+pub struct CubeTopology<'a> {
+    dimensions: Dimensions,
+    mask: Option<&'a Mask>,
+}
+
+impl<'a> CubeTopology<'a> {
+    pub fn new(dimensions: Dimensions, mask: Option<&'a Mask>) -> Self {
+        Self { dimensions, mask }
+    }
+
+    pub fn neighbour(&self, cell: Point, direction: Direction) -> Option<Point> {
+        cell.neighbour(direction, self.dimensions)
+            .filter(|p| self.contains(*p))
+    }
+}
+
+impl<'a> Topology for CubeTopology<'a> {
+    type Cell = Point;
+    type Direction = Direction;
+
+    fn direction_index(direction: Direction) -> usize {
+        direction.index()
+    }
+
+    fn opposite(direction: Direction) -> Direction {
+        direction.opposite()
+    }
+
+    fn size(&self) -> usize {
+        self.dimensions.len()
+    }
+
+    fn index(&self, cell: Point) -> usize {
+        cell.id(self.dimensions)
+    }
+
+    fn contains(&self, cell: Point) -> bool {
+        match self.mask {
+            Some(mask) => mask.is_active(cell),
+            None => self.dimensions.contains(cell),
+        }
+    }
+
+    fn cells(&self) -> Vec<Point> {
+        self.dimensions
+            .points()
+            .filter(|p| self.contains(*p))
+            .collect()
+    }
+
+    fn neighbours(&self, cell: Point) -> impl Iterator<Item = (Direction, Point)> + '_ {
+        Direction::ALL
+            .into_iter()
+            .filter_map(move |d| self.neighbour(cell, d).map(|n| (d, n)))
+    }
+}
+
+/// Neighbours of a hexagonal prism: the six sides counter-clockwise starting east, then
+/// the cells above and below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexDirection {
+    East,
+    NorthEast,
+    NorthWest,
+    West,
+    SouthWest,
+    SouthEast,
+    Up,
+    Down,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 8] = [
+        HexDirection::East,
+        HexDirection::NorthEast,
+        HexDirection::NorthWest,
+        HexDirection::West,
+        HexDirection::SouthWest,
+        HexDirection::SouthEast,
+        HexDirection::Up,
+        HexDirection::Down,
+    ];
+
+    /// The six sides in counter-clockwise order.
+    pub const SIDES: [HexDirection; 6] = [
+        HexDirection::East,
+        HexDirection::NorthEast,
+        HexDirection::NorthWest,
+        HexDirection::West,
+        HexDirection::SouthWest,
+        HexDirection::SouthEast,
+    ];
+
+    /// Position of the direction in [`HexDirection::ALL`].
+    pub fn index(self) -> usize {
+        HexDirection::ALL.iter().position(|d| *d == self).unwrap()
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            HexDirection::Up => HexDirection::Down,
+            HexDirection::Down => HexDirection::Up,
+            side => HexDirection::SIDES[(side.index() + 3) % 6],
+        }
+    }
+}
+
+/// A cell of a hexagonal prism grid in axial coordinates, `y` is the layer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HexPoint {
+    q: usize,
+    r: usize,
+    y: usize,
+}
+
+impl HexPoint {
+    pub fn new(q: usize, r: usize, y: usize) -> Self {
+        Self { q, r, y }
+    }
+    pub fn q(&self) -> usize {
+        self.q
+    }
+    pub fn r(&self) -> usize {
+        self.r
+    }
+    pub fn y(&self) -> usize {
+        self.y
+    }
+}
+
+/// A parallelogram of hexagons, `height` layers high.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HexPrism {
+    width: usize,
+    depth: usize,
+    height: usize,
+}
+
+impl HexPrism {
+    pub fn new(width: usize, depth: usize, height: usize) -> Self {
+        Self {
+            width,
+            depth,
+            height,
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn neighbour(&self, cell: HexPoint, direction: HexDirection) -> Option<HexPoint> {
+        let (q, r, y) = (cell.q as isize, cell.r as isize, cell.y as isize);
+        let (q, r, y) = match direction {
+            HexDirection::East => (q + 1, r, y),
+            HexDirection::NorthEast => (q + 1, r - 1, y),
+            HexDirection::NorthWest => (q, r - 1, y),
+            HexDirection::West => (q - 1, r, y),
+            HexDirection::SouthWest => (q - 1, r + 1, y),
+            HexDirection::SouthEast => (q, r + 1, y),
+            HexDirection::Up => (q, r, y + 1),
+            HexDirection::Down => (q, r, y - 1),
+        };
+        if q < 0 || r < 0 || y < 0 {
+            return None;
+        }
+        Some(HexPoint::new(q as usize, r as usize, y as usize)).filter(|p| self.contains(*p))
+    }
+}
+
+impl Topology for HexPrism {
+    type Cell = HexPoint;
+    type Direction = HexDirection;
+
+    fn direction_index(direction: HexDirection) -> usize {
+        direction.index()
+    }
+
+    fn opposite(direction: HexDirection) -> HexDirection {
+        direction.opposite()
+    }
+
+    fn size(&self) -> usize {
+        self.width * self.depth * self.height
+    }
+
+    fn index(&self, cell: HexPoint) -> usize {
+        cell.q + cell.r * self.width + cell.y * self.width * self.depth
+    }
+
+    fn contains(&self, cell: HexPoint) -> bool {
+        cell.q < self.width && cell.r < self.depth && cell.y < self.height
+    }
+
+    fn cells(&self) -> Vec<HexPoint> {
+        (0..self.height)
+            .flat_map(|y| {
+                (0..self.depth)
+                    .flat_map(move |r| (0..self.width).map(move |q| HexPoint::new(q, r, y)))
+            })
+            .collect()
+    }
+
+    fn neighbours(&self, cell: HexPoint) -> impl Iterator<Item = (HexDirection, HexPoint)> + '_ {
+        HexDirection::ALL
+            .into_iter()
+            .filter_map(move |d| self.neighbour(cell, d).map(|n| (d, n)))
+    }
+}
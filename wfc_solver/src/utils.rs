@@ -1,7 +1,9 @@
+use crate::topology::CubeTopology;
 use bitvec::prelude::BitVec;
 use rand::distributions::uniform::UniformSampler;
+use serde::Deserialize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Dimensions {
     pub width: usize,
     pub height: usize,
@@ -28,6 +30,16 @@ impl Dimensions {
     pub fn len(&self) -> usize {
         self.width * self.height * self.depth
     }
+    /// Every point of the grid.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let (width, height, depth) = (self.width, self.height, self.depth);
+        (0..width).flat_map(move |x| {
+            (0..height).flat_map(move |y| (0..depth).map(move |z| Point::new(x, y, z)))
+        })
+    }
+    pub fn contains(&self, point: Point) -> bool {
+        point.x < self.width && point.y < self.height && point.z < self.depth
+    }
 }
 
 impl UniformSampler for Dimensions {
@@ -58,7 +70,51 @@ impl UniformSampler for Dimensions {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+        Direction::Forward,
+        Direction::Backward,
+    ];
+
+    /// Position of the direction in [`Direction::ALL`].
+    pub fn index(self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+            Direction::Forward => 4,
+            Direction::Backward => 5,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub struct Point {
     x: usize,
     y: usize,
@@ -103,6 +159,14 @@ impl Point {
     pub fn id(&self, dimensions: Dimensions) -> usize {
         self.x + self.y * dimensions.width * dimensions.depth + self.z * dimensions.width
     }
+    /// The point with the given [`Point::id`].
+    pub fn from_id(id: usize, dimensions: Dimensions) -> Self {
+        Point::new(
+            id % dimensions.width,
+            id / (dimensions.width * dimensions.depth),
+            id / dimensions.width % dimensions.depth,
+        )
+    }
 
     directional!(rem left, x,  (1, 0, 0));
     directional!(rem down, y, (0, 1, 0));
@@ -110,12 +174,216 @@ impl Point {
     directional!(add right, x, width, (1, 0, 0));
     directional!(add up, y, height, (0, 1, 0));
     directional!(add forward, z, depth, (0, 0, 1));
+
+    pub fn neighbour(self, direction: Direction, dimensions: Dimensions) -> Option<Point> {
+        match direction {
+            Direction::Up => self.up(dimensions),
+            Direction::Down => self.down(dimensions),
+            Direction::Left => self.left(dimensions),
+            Direction::Right => self.right(dimensions),
+            Direction::Forward => self.forward(dimensions),
+            Direction::Backward => self.backward(dimensions),
+        }
+    }
+}
+
+/// The active cells of a grid. Inactive cells are not generated and act as a boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    dimensions: Dimensions,
+    active: BitVec,
+}
+
+impl Mask {
+    /// A mask with every cell active.
+    pub fn new(dimensions: Dimensions) -> Self {
+        Self {
+            dimensions,
+            active: BitVec::repeat(true, dimensions.len()),
+        }
+    }
+
+    pub fn from_fn<F: Fn(Point) -> bool>(dimensions: Dimensions, active: F) -> Self {
+        let mut mask = Self::new(dimensions);
+        for point in dimensions.points() {
+            mask.set(point, active(point));
+        }
+        mask
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn set(&mut self, point: Point, active: bool) {
+        self.active.set(point.id(self.dimensions), active);
+    }
+
+    pub fn is_active(&self, point: Point) -> bool {
+        self.dimensions.contains(point) && self.active[point.id(self.dimensions)]
+    }
+}
+
+/// The zone of every cell of a grid, as an index into the zones of a zone map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneLayout {
+    dimensions: Dimensions,
+    cells: Vec<usize>,
+}
+
+impl ZoneLayout {
+    /// A layout with every cell in the first zone.
+    pub fn new(dimensions: Dimensions) -> Self {
+        Self {
+            dimensions,
+            cells: vec![0; dimensions.len()],
+        }
+    }
+
+    pub fn from_fn<F: Fn(Point) -> usize>(dimensions: Dimensions, zone: F) -> Self {
+        let mut layout = Self::new(dimensions);
+        for point in dimensions.points() {
+            layout.set(point, zone(point));
+        }
+        layout
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn set(&mut self, point: Point, zone: usize) {
+        self.cells[point.id(self.dimensions)] = zone;
+    }
+
+    pub fn zone(&self, point: Point) -> usize {
+        self.cells[point.id(self.dimensions)]
+    }
+
+    /// Whether a neighbour of `point` lies in another zone.
+    pub fn is_border(&self, point: Point) -> bool {
+        Direction::ALL.iter().any(|direction| {
+            matches!(
+                point.neighbour(*direction, self.dimensions),
+                Some(other) if self.zone(other) != self.zone(point)
+            )
+        })
+    }
+}
+
+/// A target height per `(x, z)` column of a grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    heights: Vec<usize>,
+}
+
+impl Heightmap {
+    pub fn new(width: usize, depth: usize) -> Self {
+        Self {
+            width,
+            depth,
+            heights: vec![0; width * depth],
+        }
+    }
+
+    pub fn from_fn<F: Fn(usize, usize) -> usize>(width: usize, depth: usize, height: F) -> Self {
+        let mut heightmap = Self::new(width, depth);
+        for z in 0..depth {
+            for x in 0..width {
+                heightmap.set(x, z, height(x, z));
+            }
+        }
+        heightmap
+    }
+
+    /// Reads a grayscale image with one byte per pixel, row by row along z. White maps to
+    /// `max_height`, black to zero.
+    pub fn from_gray(width: usize, depth: usize, pixels: &[u8], max_height: usize) -> Self {
+        Self::from_fn(width, depth, |x, z| {
+            let gray = pixels.get(x + z * width).copied().unwrap_or(0);
+            (gray as f32 / 255.0 * max_height as f32).round() as usize
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set(&mut self, x: usize, z: usize, height: usize) {
+        self.heights[x + z * self.width] = height;
+    }
+
+    pub fn height(&self, x: usize, z: usize) -> usize {
+        self.heights[x + z * self.width]
+    }
+}
+
+/// A value per cell of a grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightGrid {
+    dimensions: Dimensions,
+    values: Vec<f32>,
+}
+
+impl WeightGrid {
+    pub fn new(dimensions: Dimensions, value: f32) -> Self {
+        Self {
+            dimensions,
+            values: vec![value; dimensions.len()],
+        }
+    }
+
+    pub fn from_fn<F: Fn(Point) -> f32>(dimensions: Dimensions, value: F) -> Self {
+        let mut grid = Self::new(dimensions, 0.0);
+        for point in dimensions.points() {
+            grid.set(point, value(point));
+        }
+        grid
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn set(&mut self, point: Point, value: f32) {
+        self.values[point.id(self.dimensions)] = value;
+    }
+
+    pub fn get(&self, point: Point) -> f32 {
+        self.values[point.id(self.dimensions)]
+    }
+}
+
+/// The cells that could still hold one of `variants`, and those that certainly hold one, by
+/// [`Point::id`].
+#[derive(Debug, Clone)]
+pub struct TrackedVariants {
+    pub variants: BitVec,
+    pub possible: BitVec,
+    pub definite: BitVec,
+}
+
+impl TrackedVariants {
+    fn refresh(&mut self, id: usize, domain: &BitVec) {
+        let possible = domain.iter_ones().any(|v| self.variants[v]);
+        self.possible.set(id, possible);
+        self.definite
+            .set(id, possible && domain.iter_ones().all(|v| self.variants[v]));
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FieldGrid {
     dimensions: Dimensions,
     tiles: Vec<BitVec>,
+    mask: Option<Mask>,
+    tracked: Vec<TrackedVariants>,
 }
 
 impl FieldGrid {
@@ -123,11 +391,76 @@ impl FieldGrid {
         Self {
             tiles: vec![initial; dimensions.len()],
             dimensions,
+            mask: None,
+            tracked: Vec::new(),
         }
     }
 
+    /// Restricts the grid to the active cells of `mask`, the other cells are ignored.
+    pub fn with_mask(mut self, mask: Option<Mask>) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn mask(&self) -> Option<&Mask> {
+        self.mask.as_ref()
+    }
+
+    pub fn is_active(&self, point: Point) -> bool {
+        self.mask
+            .as_ref()
+            .map_or(self.dimensions.contains(point), |mask| mask.is_active(point))
+    }
+
+    /// The topology of the grid, next to mutable access to the domains of its cells.
+    pub fn split_mut(&mut self) -> (CubeTopology<'_>, &mut [BitVec]) {
+        (
+            CubeTopology::new(self.dimensions, self.mask.as_ref()),
+            &mut self.tiles,
+        )
+    }
+
+    /// The points of the grid that are not masked out.
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        self.dimensions.points().filter(|p| self.is_active(*p))
+    }
+
     pub fn set(&mut self, point: Point, vec: BitVec) {
         self.tiles[point.id(self.dimensions)] = vec;
+        self.refresh(point);
+    }
+
+    /// Starts keeping the active cells that can hold `variants` up to date, so constraints do
+    /// not have to scan the grid. Returns the index of the set for [`FieldGrid::tracked`].
+    pub fn track(&mut self, variants: BitVec) -> usize {
+        let len = self.dimensions.len();
+        let mut tracked = TrackedVariants {
+            variants,
+            possible: BitVec::repeat(false, len),
+            definite: BitVec::repeat(false, len),
+        };
+        for point in self.points() {
+            let id = point.id(self.dimensions);
+            tracked.refresh(id, &self.tiles[id]);
+        }
+        self.tracked.push(tracked);
+        self.tracked.len() - 1
+    }
+
+    pub fn tracked(&self, index: usize) -> &TrackedVariants {
+        &self.tracked[index]
+    }
+
+    /// Updates the tracked variants after the domain of `point` changed through
+    /// [`FieldGrid::split_mut`] or [`FieldGrid::get_mut`].
+    pub fn refresh(&mut self, point: Point) {
+        if !self.is_active(point) {
+            return;
+        }
+        let id = point.id(self.dimensions);
+        for tracked in &mut self.tracked {
+            tracked.refresh(id, &self.tiles[id]);
+        }
     }
 
     pub fn get(&self, point: Point) -> Option<&BitVec> {
@@ -147,10 +480,12 @@ impl FieldGrid {
     }
 
     pub fn is_satisfiable(&self) -> bool {
-        self.tiles.iter().all(|x| x.count_ones() >= 1)
+        self.points()
+            .all(|p| self.tiles[p.id(self.dimensions)].count_ones() >= 1)
     }
     pub fn is_complete(&self) -> bool {
-        self.tiles.iter().all(|x| x.count_ones() == 1)
+        self.points()
+            .all(|p| self.tiles[p.id(self.dimensions)].count_ones() == 1)
     }
 
     pub fn should_update(&self, point: Point, new_domain: &BitVec) -> bool {
@@ -164,9 +499,9 @@ impl FieldGrid {
      */
     pub fn update(&mut self, point: Point, new_domain: &BitVec) -> Option<&BitVec> {
         if self.should_update(point, new_domain) {
-            let grid = self.get_mut(point).unwrap();
-            *grid &= new_domain;
-            return Some(grid);
+            *self.get_mut(point).unwrap() &= new_domain;
+            self.refresh(point);
+            return self.get(point);
         } else {
             return None;
         }
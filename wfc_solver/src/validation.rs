@@ -0,0 +1,221 @@
+use crate::{
+    description::{CompiledDescription, ProblemDescription, TileRotation},
+    error::ProblemError,
+    solver::naive::{update_initial_sides, update_placement},
+    utils::Direction,
+};
+use bitvec::prelude::BitVec;
+use std::fmt::{Debug, Display};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue<D> {
+    /// A face uses a socket that is not listed in `connections`.
+    UndeclaredSocket {
+        tile: D,
+        face: Direction,
+        connection: String,
+    },
+    /// No variant of any tile can be placed against this face.
+    UnmatchedFace { tile: D, face: Direction },
+    /// The tile is removed from every cell by the boundary conditions and placement rules alone.
+    NeverPlaceable { tile: D },
+    /// The boundary conditions alone already empty a cell.
+    UnsatisfiableBoundary,
+    /// A non-symmetric socket that does not connect both ways: no face of any tile has it
+    /// flipped the other way, or another face declares the same socket symmetric, which then
+    /// connects to it in either orientation.
+    AsymmetricSocket {
+        tile: D,
+        face: Direction,
+        connection: String,
+    },
+    /// The description fails [`ProblemDescription::check`], the checks that need the
+    /// compiled description are skipped.
+    InvalidDescription(ProblemError),
+    DuplicateTileId { tile: D, count: usize },
+}
+
+impl<D: Debug> Display for ValidationIssue<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::UndeclaredSocket {
+                tile,
+                face,
+                connection,
+            } => write!(
+                f,
+                "Tile {:?} uses undeclared socket {:?} on its {:?} face",
+                tile, connection, face
+            ),
+            ValidationIssue::UnmatchedFace { tile, face } => write!(
+                f,
+                "The {:?} face of tile {:?} has no possible neighbour",
+                face, tile
+            ),
+            ValidationIssue::NeverPlaceable { tile } => write!(
+                f,
+                "Tile {:?} can never be placed with the current boundary",
+                tile
+            ),
+            ValidationIssue::UnsatisfiableBoundary => {
+                f.write_str("The boundary conditions cannot be satisfied")
+            }
+            ValidationIssue::AsymmetricSocket {
+                tile,
+                face,
+                connection,
+            } => write!(
+                f,
+                "Socket {:?} on the {:?} face of tile {:?} has no flipped counterpart",
+                connection, face, tile
+            ),
+            ValidationIssue::InvalidDescription(err) => write!(f, "{}", err),
+            ValidationIssue::DuplicateTileId { tile, count } => {
+                write!(f, "Tile id {:?} is used by {} tiles", tile, count)
+            }
+        }
+    }
+}
+
+impl<D: Clone + PartialEq + Debug> ProblemDescription<D> {
+    /// Looks for mistakes in the tileset that would otherwise only show up as an
+    /// unsatisfiable problem when solving.
+    pub fn validate(&self) -> Vec<ValidationIssue<D>> {
+        let mut issues = Vec::new();
+        issues.extend(self.duplicate_ids());
+        issues.extend(self.undeclared_sockets());
+        issues.extend(self.asymmetric_sockets());
+        if let Err(err) = self.check() {
+            issues.push(ValidationIssue::InvalidDescription(err));
+            return issues;
+        }
+
+        let all = self.compile_all();
+        issues.extend(self.unmatched_faces(&all));
+        issues.extend(self.never_placeable(&self.compile()));
+        issues
+    }
+
+    fn duplicate_ids(&self) -> Vec<ValidationIssue<D>> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(index, tile)| !self.tiles[..*index].iter().any(|t| t.id == tile.id))
+            .map(|(_, tile)| (tile, self.tiles.iter().filter(|t| t.id == tile.id).count()))
+            .filter(|(_, count)| *count > 1)
+            .map(|(tile, count)| ValidationIssue::DuplicateTileId {
+                tile: tile.id.clone(),
+                count,
+            })
+            .collect()
+    }
+
+    fn undeclared_sockets(&self) -> Vec<ValidationIssue<D>> {
+        let mut issues = Vec::new();
+        for tile in &self.tiles {
+            for face in Direction::ALL {
+                for connection in tile.connections(face) {
+                    if !self.connections.iter().any(|c| c == connection) {
+                        issues.push(ValidationIssue::UndeclaredSocket {
+                            tile: tile.id.clone(),
+                            face,
+                            connection: connection.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    /// Checks the declared sockets rather than the compiled adjacency, which is symmetric by
+    /// construction. Any horizontal face can meet any other once the tiles are rotated.
+    fn asymmetric_sockets(&self) -> Vec<ValidationIssue<D>> {
+        let faces = [
+            Direction::Left,
+            Direction::Right,
+            Direction::Forward,
+            Direction::Backward,
+        ];
+        let mut sockets = Vec::new();
+        for tile in &self.tiles {
+            for face in faces {
+                sockets.extend(tile.rotated_face(face, TileRotation::R0).unwrap());
+            }
+        }
+
+        let mut issues = Vec::new();
+        for tile in &self.tiles {
+            for face in faces {
+                for socket in tile.rotated_face(face, TileRotation::R0).unwrap() {
+                    let same = sockets
+                        .iter()
+                        .filter(|other| other.connection == socket.connection);
+                    let mixed = same.clone().any(|other| other.symmetry);
+                    let paired = same
+                        .clone()
+                        .any(|other| !other.symmetry && other.flipped != socket.flipped);
+                    if !socket.symmetry && (mixed || !paired) {
+                        issues.push(ValidationIssue::AsymmetricSocket {
+                            tile: tile.id.clone(),
+                            face,
+                            connection: socket.connection.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    fn unmatched_faces(&self, compiled: &CompiledDescription) -> Vec<ValidationIssue<D>> {
+        let mut issues = Vec::new();
+        for index in 0..compiled.len() {
+            let data = compiled.data(index);
+            if data.rotation != TileRotation::R0 || data.flipped {
+                continue;
+            }
+            for face in Direction::ALL {
+                if compiled.direction(face, index).not_any() {
+                    issues.push(ValidationIssue::UnmatchedFace {
+                        tile: self.tiles[data.tile_index].id.clone(),
+                        face,
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    fn never_placeable(&self, compiled: &CompiledDescription) -> Vec<ValidationIssue<D>> {
+        if compiled.len() == 0 || self.dimensions.len() == 0 {
+            return Vec::new();
+        }
+
+        let mut grid = compiled.grid();
+        let propagated = update_placement(&mut grid, compiled)
+            .and_then(|_| update_initial_sides(&mut grid, compiled));
+        if propagated.is_err() || !grid.is_satisfiable() {
+            return vec![ValidationIssue::UnsatisfiableBoundary];
+        }
+
+        let placeable = grid
+            .tiles()
+            .iter()
+            .fold(BitVec::repeat(false, compiled.len()), |acc, x| acc | x.clone());
+
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| !tile.decorative)
+            .filter(|(index, _)| {
+                !placeable
+                    .iter_ones()
+                    .any(|v| compiled.data(v).tile_index == *index)
+            })
+            .map(|(_, tile)| ValidationIssue::NeverPlaceable {
+                tile: tile.id.clone(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,160 @@
+//! Fixtures shared by the integration tests, every test file uses only some of them.
+#![allow(dead_code)]
+
+use wfc_solver::{
+    description::{HorizontalConnection, ProblemDescription, Tile, VerticalConnection},
+    description2d::{ProblemDescription2d, Tile2d},
+    utils::Dimensions,
+};
+
+/// A face with a single symmetric socket.
+pub fn side(connection: &str) -> Vec<HorizontalConnection> {
+    vec![HorizontalConnection {
+        connection: connection.into(),
+        flipped: false,
+        symmetry: true,
+    }]
+}
+
+/// A rotatable 2D tile with the sockets of its left, right, forward and backward faces.
+pub fn tile2d(
+    id: &'static str,
+    [left, right, forward, backward]: [&str; 4],
+) -> Tile2d<&'static str> {
+    Tile2d {
+        id,
+        left: side(left),
+        right: side(right),
+        forward: side(forward),
+        backward: side(backward),
+        can_rotate: true,
+        can_flip: false,
+        weight: 1.0,
+        metadata: Default::default(),
+        tags: Vec::new(),
+        placement: Default::default(),
+        decorative: false,
+    }
+}
+
+/// Grass, straight roads and road ends, roads can only end in an "end" tile.
+pub fn road_description(width: usize, height: usize) -> ProblemDescription2d<&'static str> {
+    ProblemDescription2d {
+        connections: vec!["grass".into(), "road".into()],
+        width,
+        height,
+        tiles: vec![
+            tile2d("grass", ["grass", "grass", "grass", "grass"]),
+            tile2d("road", ["road", "road", "grass", "grass"]),
+            tile2d("end", ["road", "grass", "grass", "grass"]),
+        ],
+        ..Default::default()
+    }
+}
+
+/// Air, a straight and a corner piece on a 3x3x3 grid.
+pub fn basic_straight_air_description() -> ProblemDescription<usize> {
+    ProblemDescription {
+        connections: vec!["air".into(), "half".into(), "full".into()],
+        dimensions: Dimensions::new(3, 3, 3),
+        tiles: vec![
+            Tile {
+                id: 0,
+                can_flip: false,
+                can_rotate: true,
+                up: vec![VerticalConnection {
+                    connection: "air".into(),
+                }],
+                down: vec![VerticalConnection {
+                    connection: "air".into(),
+                }],
+                forward: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                backward: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                left: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                right: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                ..Default::default()
+            },
+            Tile {
+                id: 1, // straight,
+                can_flip: false,
+                can_rotate: true,
+                up: vec![VerticalConnection {
+                    connection: "air".into(),
+                }],
+                down: vec![VerticalConnection {
+                    connection: "full".into(),
+                }],
+                left: vec![HorizontalConnection {
+                    connection: "half".into(),
+                    flipped: false,
+                    symmetry: false,
+                }],
+                right: vec![HorizontalConnection {
+                    connection: "half".into(),
+                    flipped: true,
+                    symmetry: false,
+                }],
+                forward: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                backward: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                ..Default::default()
+            },
+            Tile {
+                id: 2, // corner
+                can_flip: false,
+                can_rotate: true,
+                up: vec![VerticalConnection {
+                    connection: "air".into(),
+                }],
+                down: vec![VerticalConnection {
+                    connection: "full".into(),
+                }],
+                left: vec![HorizontalConnection {
+                    connection: "half".into(),
+                    flipped: false,
+                    symmetry: false,
+                }],
+                right: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                forward: vec![HorizontalConnection {
+                    connection: "half".into(),
+                    flipped: true,
+                    symmetry: false,
+                }],
+                backward: vec![HorizontalConnection {
+                    connection: "air".into(),
+                    flipped: false,
+                    symmetry: true,
+                }],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
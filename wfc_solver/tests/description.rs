@@ -1,10 +1,10 @@
 use bitvec::prelude::*;
 use wfc_solver::{
     description::{
-        CompiledDescription, HorizontalConnection, ProblemDescription, Tile, TileData,
+        CompiledDescription, HorizontalConnection, Placement, ProblemDescription, Tile, TileData,
         TileRotation, VerticalConnection,
     },
-    utils::Dimensions,
+    utils::{Dimensions, Direction},
 };
 
 #[test]
@@ -75,8 +75,23 @@ fn test_compile() {
     assert_eq!(ref_compiled, compiled);
 }
 
+#[test]
+fn test_compile_prunes_unplaceable_variants() {
+    let mut desc = basic_straight_air_description();
+    desc.dimensions = Dimensions::new(1, 1, 1);
+
+    let (compiled, removed) = desc.compile_with_report();
+
+    assert_eq!(4, compiled.len());
+    assert!(compiled.transformation.iter().all(|td| td.tile_index == 0));
+    assert_eq!(8, removed.len());
+    assert!(removed.iter().all(|td| td.tile_index != 0));
+    assert_eq!(bitvec!(1, 1, 1, 1), *compiled.left(0));
+    assert_eq!(bitvec!(1, 1, 1, 1), *compiled.boundary(Direction::Up));
+}
+
 fn basic_straight_air_compiled() -> CompiledDescription {
-    CompiledDescription {
+    let mut compiled = CompiledDescription {
         dimensions: Dimensions::new(3, 3, 3),
         transformation: vec![
             TileData::new(0, TileRotation::R0, false),
@@ -92,6 +107,8 @@ fn basic_straight_air_compiled() -> CompiledDescription {
             TileData::new(2, TileRotation::R180, false),
             TileData::new(2, TileRotation::R270, false),
         ],
+        weights: vec![1.0; 12],
+        placement: vec![Placement::default(); 12],
         up: vec![
             // Air
             bitvec!(1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0),
@@ -194,7 +211,26 @@ fn basic_straight_air_compiled() -> CompiledDescription {
             bitvec!(0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0),
             bitvec!(0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0),
         ],
-    }
+        boundary: vec![
+            // Up
+            bitvec!(1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1),
+            // Down
+            bitvec!(1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1),
+            // Left
+            bitvec!(1, 1, 1, 1, 0, 1, 0, 1, 0, 1, 1, 0),
+            // Right
+            bitvec!(1, 1, 1, 1, 0, 1, 0, 1, 1, 0, 0, 1),
+            // Forward
+            bitvec!(1, 1, 1, 1, 1, 0, 1, 0, 0, 0, 1, 1),
+            // Backward
+            bitvec!(1, 1, 1, 1, 1, 0, 1, 0, 1, 1, 0, 0),
+        ],
+        ..Default::default()
+    };
+    // Without a mask tile, masked out cells act like the outside of the grid.
+    compiled.mask_boundary = compiled.boundary.clone();
+    compiled.decorative = BitVec::repeat(false, compiled.len());
+    compiled
 }
 
 fn basic_straight_air_description() -> ProblemDescription<usize> {
@@ -232,6 +268,7 @@ fn basic_straight_air_description() -> ProblemDescription<usize> {
                     flipped: false,
                     symmetry: true,
                 }],
+                ..Default::default()
             },
             Tile {
                 id: 1, // straight,
@@ -263,6 +300,7 @@ fn basic_straight_air_description() -> ProblemDescription<usize> {
                     flipped: false,
                     symmetry: true,
                 }],
+                ..Default::default()
             },
             Tile {
                 id: 2, // corner
@@ -294,7 +332,9 @@ fn basic_straight_air_description() -> ProblemDescription<usize> {
                     flipped: false,
                     symmetry: true,
                 }],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     }
 }
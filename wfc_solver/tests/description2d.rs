@@ -0,0 +1,43 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{FixedTile, TileRotation},
+    description2d::ProblemDescription2d,
+    utils::Point,
+};
+
+#[test]
+fn test_solve_2d() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, road_description().into_3d()).unwrap();
+
+    let dimensions = solution.grid.dimensions();
+    assert_eq!(1, dimensions.height());
+    for x in 0..4 {
+        for y in 0..3 {
+            assert!(solution.tile(Point::new(x, 0, y)).is_some());
+        }
+    }
+}
+
+#[test]
+fn test_solve_2d_sides() {
+    // A straight road can not end at the grass boundary.
+    let mut description = road_description();
+    description.fixed = vec![FixedTile::new(
+        Point::new(1, 0, 1),
+        TileRotation::R0,
+        "road",
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    assert!(wfc_solver::solve(&mut rng, description.into_3d()).is_err());
+}
+
+/// Grass and straight roads, a road can not end.
+fn road_description() -> ProblemDescription2d<&'static str> {
+    let mut description = common::road_description(4, 3);
+    description.tiles.retain(|tile| tile.id != "end");
+    description
+}
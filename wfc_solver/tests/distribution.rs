@@ -0,0 +1,64 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::Distribution,
+    description2d::{ProblemDescription2d, Tile2d},
+    error::ProblemError,
+};
+
+#[test]
+fn test_distribution() {
+    for seed in 0..4 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let mut description = field_description();
+        description.distribution = Some(Distribution::new(
+            vec![("wheat", 0.5), ("barley", 0.3), ("fallow", 0.2)],
+            0.05,
+        ));
+        let solution = wfc_solver::solve(&mut rng, description).unwrap();
+
+        for ((id, share), target) in solution.histogram().into_iter().zip([0.5, 0.3, 0.2]) {
+            assert!(
+                (share - target).abs() <= 0.05,
+                "{} ended up at {} instead of {}",
+                id,
+                share,
+                target
+            );
+        }
+    }
+}
+
+#[test]
+fn test_distribution_unknown_tile() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut description = field_description();
+    description.distribution = Some(Distribution::new(vec![("wheat", 0.5), ("rye", 0.5)], 0.1));
+
+    assert_eq!(
+        Err(ProblemError::UnknownDistributionTile(1)),
+        wfc_solver::solve(&mut rng, description).map(|_| ())
+    );
+}
+
+/// Fields that fit next to each other in any arrangement, wheat is by far the most likely.
+fn field_description() -> wfc_solver::description::ProblemDescription<&'static str> {
+    let tile = |id, weight| Tile2d {
+        weight,
+        ..common::tile2d(id, ["soil"; 4])
+    };
+
+    ProblemDescription2d {
+        connections: vec!["soil".into()],
+        width: 10,
+        height: 10,
+        tiles: vec![
+            tile("wheat", 10.0),
+            tile("barley", 1.0),
+            tile("fallow", 1.0),
+        ],
+        ..Default::default()
+    }
+    .into_3d()
+}
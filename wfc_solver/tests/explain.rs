@@ -0,0 +1,63 @@
+mod common;
+
+use common::basic_straight_air_description;
+use wfc_solver::{
+    description::{FixedTile, TileRotation},
+    explain::Constraint,
+    utils::{Dimensions, Direction, Point},
+};
+
+#[test]
+fn test_explain_boundary_and_fixed() {
+    let mut desc = basic_straight_air_description();
+    desc.dimensions = Dimensions::new(1, 1, 1);
+    desc.fixed = vec![FixedTile::new(Point::new(0, 0, 0), TileRotation::R0, 1)];
+
+    let explanation = desc.explain().unwrap();
+
+    assert_eq!(2, explanation.constraints.len());
+    match &explanation.constraints[0] {
+        Constraint::Boundary {
+            direction,
+            outside,
+            sockets,
+        } => {
+            assert_eq!(Direction::Right, *direction);
+            assert_eq!(0, *outside);
+            assert_eq!(vec!["air".to_string()], *sockets);
+        }
+        c => panic!("Expected a boundary, got {:?}", c),
+    }
+    assert!(explanation.constraints[0]
+        .to_string()
+        .starts_with("The Right boundary 0 forces"));
+    assert!(matches!(explanation.constraints[1], Constraint::Fixed(_)));
+}
+
+#[test]
+fn test_explain_adjacency() {
+    let mut desc = basic_straight_air_description();
+    desc.fixed = vec![FixedTile::new(Point::new(1, 1, 1), TileRotation::R0, 1)];
+
+    let explanation = desc.explain().unwrap();
+
+    assert_eq!(1, explanation.constraints.len());
+    assert!(matches!(explanation.constraints[0], Constraint::Fixed(_)));
+
+    let conflict = explanation.conflict.unwrap();
+    assert_eq!(Point::new(1, 0, 1), conflict.point);
+    assert_eq!(Point::new(1, 1, 1), conflict.neighbour);
+    assert_eq!(Direction::Up, conflict.direction);
+    assert_eq!(1, conflict.neighbour_tiles.len());
+    assert_eq!(
+        vec!["full".to_string()],
+        conflict.neighbour_tiles[0].sockets
+    );
+}
+
+#[test]
+fn test_explain_satisfiable() {
+    let desc = basic_straight_air_description();
+
+    assert!(desc.explain().is_none());
+}
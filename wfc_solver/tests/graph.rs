@@ -0,0 +1,83 @@
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{HorizontalConnection, Tile, VerticalConnection},
+    error::ProblemError,
+    graph::{GraphDescription, GraphEdge},
+    utils::Direction,
+};
+
+#[test]
+fn test_solve_graph() {
+    let description = dungeon_description();
+    let edges = description.edges.clone();
+    let labels = description.labels.clone();
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::graph::solve_graph(&mut rng, description.clone()).unwrap();
+
+        for edge in &edges {
+            let from = solution.domains[edge.from].first_one().unwrap();
+            let to = solution.domains[edge.to].first_one().unwrap();
+            assert!(solution.compiled.direction(labels[&edge.label], from)[to]);
+        }
+        assert!(solution.tile(3).is_some());
+    }
+}
+
+#[test]
+fn test_unknown_edge_label() {
+    let mut description = dungeon_description();
+    description.edges.push(GraphEdge::new(0, "down", 3));
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    assert_eq!(
+        Some(ProblemError::UnknownEdgeLabel("down".into())),
+        wfc_solver::graph::solve_graph(&mut rng, description).err()
+    );
+}
+
+/// Nodes 0, 2 and 3 lie west, east and north of node 1.
+fn dungeon_description() -> GraphDescription<&'static str> {
+    let side = |connection: &str| {
+        vec![HorizontalConnection {
+            connection: connection.into(),
+            flipped: false,
+            symmetry: true,
+        }]
+    };
+    let vertical = || {
+        vec![VerticalConnection {
+            connection: "floor".into(),
+        }]
+    };
+    let tile = |id, sides: [&str; 4]| Tile {
+        id,
+        up: vertical(),
+        down: vertical(),
+        left: side(sides[0]),
+        right: side(sides[1]),
+        forward: side(sides[2]),
+        backward: side(sides[3]),
+        can_rotate: true,
+        ..Default::default()
+    };
+
+    GraphDescription {
+        connections: vec!["door".into(), "wall".into(), "floor".into()],
+        tiles: vec![
+            tile("hall", ["door", "door", "door", "wall"]),
+            tile("room", ["wall", "wall", "door", "wall"]),
+        ],
+        nodes: 4,
+        edges: vec![
+            GraphEdge::new(0, "east", 1),
+            GraphEdge::new(1, "east", 2),
+            GraphEdge::new(1, "north", 3),
+        ],
+        labels: [("east", Direction::Right), ("north", Direction::Forward)]
+            .into_iter()
+            .map(|(label, direction)| (label.to_string(), direction))
+            .collect(),
+    }
+}
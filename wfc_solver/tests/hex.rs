@@ -0,0 +1,102 @@
+use rand::SeedableRng;
+use wfc_solver::{
+    error::ProblemError,
+    hex::{HexDescription, HexTile},
+    topology::{HexDirection, HexPoint, HexPrism, Topology},
+};
+
+#[test]
+fn test_hex_neighbours() {
+    let prism = HexPrism::new(3, 3, 3);
+    let center = HexPoint::new(1, 1, 1);
+
+    let neighbours = HexDirection::ALL
+        .iter()
+        .filter_map(|d| prism.neighbour(center, *d))
+        .collect::<Vec<_>>();
+    assert_eq!(8, neighbours.len());
+
+    for direction in HexDirection::ALL {
+        let neighbour = prism.neighbour(center, direction).unwrap();
+        assert_eq!(
+            Some(center),
+            prism.neighbour(neighbour, direction.opposite())
+        );
+    }
+
+    assert_eq!(
+        None,
+        prism.neighbour(HexPoint::new(0, 0, 0), HexDirection::West)
+    );
+    assert_eq!(
+        None,
+        prism.neighbour(HexPoint::new(2, 0, 0), HexDirection::NorthEast)
+    );
+}
+
+#[test]
+fn test_hex_solve() {
+    let prism = HexPrism::new(5, 5, 1);
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(3);
+    let solution = wfc_solver::hex::solve_hex(&mut rng, coast_description(prism)).unwrap();
+
+    for point in prism.cells() {
+        let variant = solution.variant(point).unwrap();
+        let tile = solution.tile(point).unwrap();
+        for direction in HexDirection::SIDES {
+            if let Some(neighbour) = prism.neighbour(point, direction) {
+                let other = solution.variant(neighbour).unwrap();
+                let other_tile = solution.tile(neighbour).unwrap();
+                let sockets = tile.sockets(direction, variant.rotation);
+                assert!(other_tile
+                    .sockets(direction.opposite(), other.rotation)
+                    .iter()
+                    .any(|s| sockets.contains(s)));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hex_contradiction_cause() {
+    let tile = |id| HexTile {
+        id,
+        up: vec!["sky".into()],
+        down: vec!["ground".into()],
+        sides: ["east", "land", "land", "west", "land", "land"].map(|s| vec![s.to_string()]),
+        can_rotate: false,
+        weight: 1.0,
+    };
+    let description = HexDescription {
+        dimensions: HexPrism::new(2, 1, 1),
+        tiles: vec![tile("grass"), tile("sand")],
+    };
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let result = wfc_solver::hex::solve_hex(&mut rng, description);
+
+    assert!(matches!(
+        result.err(),
+        Some(ProblemError::HexContradiction(_, Some(_)))
+    ));
+}
+
+fn coast_description(dimensions: HexPrism) -> HexDescription<&'static str> {
+    let tile = |id, sides: [&str; 6]| HexTile {
+        id,
+        up: vec!["sky".into()],
+        down: vec!["ground".into()],
+        sides: sides.map(String::from).map(|s| vec![s]),
+        can_rotate: true,
+        weight: 1.0,
+    };
+
+    HexDescription {
+        dimensions,
+        tiles: vec![
+            tile("grass", ["land"; 6]),
+            tile("water", ["sea"; 6]),
+            tile("coast", ["land", "land", "land", "sea", "sea", "sea"]),
+        ],
+    }
+}
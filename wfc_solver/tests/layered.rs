@@ -0,0 +1,80 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{FixedTile, ProblemDescription, Tile, TileRotation, VerticalConnection},
+    error::ProblemError,
+    layered::solve_layered,
+    utils::{Dimensions, Point},
+};
+
+#[test]
+fn test_layered() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut ground = storey("shop", "ground", "floor", 4);
+    ground.fixed = vec![FixedTile::new(
+        Point::new(1, 0, 1),
+        TileRotation::R0,
+        "shop",
+    )];
+    let upper = storey("home", "floor", "air", 4);
+
+    let solution = solve_layered(&mut rng, vec![ground, upper]).unwrap();
+    assert_eq!(Dimensions::new(4, 2, 4), solution.dimensions());
+    assert_eq!("home", solution.tile(Point::new(1, 1, 1)).unwrap().id);
+
+    for x in 0..4 {
+        for z in 0..4 {
+            let below = solution.tile(Point::new(x, 0, z)).unwrap().id;
+            let above = solution.tile(Point::new(x, 1, z)).unwrap().id;
+            assert_eq!(below == "shop", above == "home");
+        }
+    }
+}
+
+#[test]
+fn test_layered_dimensions() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let ground = storey("shop", "ground", "floor", 4);
+    let upper = storey("home", "floor", "air", 3);
+
+    assert_eq!(
+        Err(ProblemError::LayerDimensions(1)),
+        solve_layered(&mut rng, vec![ground, upper]).map(|_| ())
+    );
+}
+
+/// A storey of air and one kind of room, the room stands on `down` and carries `up`.
+fn storey(
+    room: &'static str,
+    down: &str,
+    up: &str,
+    width: usize,
+) -> ProblemDescription<&'static str> {
+    let vertical = |connection: &str| {
+        vec![VerticalConnection {
+            connection: connection.into(),
+        }]
+    };
+    let tile = |id, down: &str, up: &str| Tile {
+        id,
+        up: vertical(up),
+        down: vertical(down),
+        left: common::side("wall"),
+        right: common::side("wall"),
+        forward: common::side("wall"),
+        backward: common::side("wall"),
+        can_rotate: true,
+        ..Default::default()
+    };
+
+    ProblemDescription {
+        connections: ["wall", "air", "ground", "floor"]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        dimensions: Dimensions::new(width, 1, width),
+        tiles: vec![tile("air", "air", "air"), tile(room, down, up)],
+        ..Default::default()
+    }
+}
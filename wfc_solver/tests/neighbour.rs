@@ -78,6 +78,7 @@ fn basic_straight_air_description() -> ProblemDescription<usize> {
                     flipped: false,
                     symmetry: true,
                 }],
+                ..Default::default()
             },
             Tile {
                 id: 1, // straight,
@@ -109,6 +110,7 @@ fn basic_straight_air_description() -> ProblemDescription<usize> {
                     flipped: false,
                     symmetry: true,
                 }],
+                ..Default::default()
             },
             Tile {
                 id: 2, // corner
@@ -140,7 +142,9 @@ fn basic_straight_air_description() -> ProblemDescription<usize> {
                     flipped: false,
                     symmetry: true,
                 }],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     }
 }
@@ -0,0 +1,107 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{PathConstraint, PathShape, TileSelector},
+    description2d::ProblemDescription2d,
+    error::ProblemError,
+    utils::{Direction, Point},
+};
+
+#[test]
+fn test_path_cells() {
+    for shape in [
+        PathShape::Polyline(vec![
+            Point::new(0, 0, 0),
+            Point::new(3, 0, 2),
+            Point::new(3, 2, 4),
+        ]),
+        PathShape::Spline(vec![
+            Point::new(0, 0, 0),
+            Point::new(4, 0, 1),
+            Point::new(1, 0, 5),
+        ]),
+    ] {
+        let cells = shape.cells();
+        assert_eq!(Some(&Point::new(0, 0, 0)), cells.first());
+        for pair in cells.windows(2) {
+            let distance = [
+                pair[0].x().abs_diff(pair[1].x()),
+                pair[0].y().abs_diff(pair[1].y()),
+                pair[0].z().abs_diff(pair[1].z()),
+            ];
+            assert_eq!(1, distance.iter().sum::<usize>());
+        }
+    }
+}
+
+#[test]
+fn test_spline_overshoot() {
+    // Leaving (8, 0, 0) the curve swings out past x = 8 before it turns back.
+    let shape = PathShape::Spline(vec![
+        Point::new(0, 0, 0),
+        Point::new(8, 0, 0),
+        Point::new(8, 0, 8),
+    ]);
+    for cell in shape.cells() {
+        assert!(
+            cell.x() <= 8 && cell.z() <= 8,
+            "{:?} is outside the points",
+            cell
+        );
+    }
+
+    let mut description = common::road_description(9, 9);
+    description.paths = vec![PathConstraint::new(
+        shape,
+        TileSelector::Tile("road"),
+        "road",
+    )];
+    assert_eq!(Ok(()), description.into_3d().check());
+}
+
+#[test]
+fn test_solve_path() {
+    let mut description = road_description();
+    description.paths = vec![PathConstraint::new(
+        PathShape::Polyline(vec![Point::new(0, 0, 1), Point::new(3, 0, 1)]),
+        TileSelector::Tag("road".into()),
+        "road",
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, description.clone().into_3d()).unwrap();
+    for x in 0..4 {
+        let point = Point::new(x, 0, 1);
+        let data = solution.tile_data(point).unwrap();
+        let tile = solution.tile(point).unwrap();
+        assert!(tile.tags.contains(&"road".to_string()));
+        let along = match x {
+            0 => vec![Direction::Right],
+            3 => vec![Direction::Left],
+            _ => vec![Direction::Left, Direction::Right],
+        };
+        for direction in along {
+            assert!(tile
+                .rotated_connections(direction, data.rotation)
+                .contains(&"road"));
+        }
+    }
+
+    description.paths[0].shape =
+        PathShape::Polyline(vec![Point::new(0, 0, 1), Point::new(4, 0, 1)]);
+    assert_eq!(
+        Some(ProblemError::PathOutOfBounds(Point::new(4, 0, 1))),
+        wfc_solver::solve(&mut rng, description.into_3d()).err()
+    );
+}
+
+/// The shared roads, with a "road" tag on every tile that carries one.
+fn road_description() -> ProblemDescription2d<&'static str> {
+    let mut description = common::road_description(4, 3);
+    for tile in &mut description.tiles {
+        let tag = if tile.id == "grass" { "grass" } else { "road" };
+        tile.tags = vec![tag.into()];
+    }
+    description
+}
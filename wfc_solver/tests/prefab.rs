@@ -0,0 +1,74 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{Prefab, PrefabPlacement, TileRotation},
+    error::ProblemError,
+    utils::{Dimensions, Point},
+};
+
+#[test]
+fn test_prefab_at_point() {
+    let mut description = common::road_description(5, 5).into_3d();
+    description.prefabs = vec![PrefabPlacement::new(
+        street(),
+        Some(Point::new(1, 0, 1)),
+        Some(TileRotation::R90),
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, description).unwrap();
+    // Turned by 90 degrees the street runs along z, starting at its far end.
+    for (z, id) in [(3, "end"), (2, "road"), (1, "end")] {
+        assert_eq!(id, solution.tile(Point::new(1, 0, z)).unwrap().id);
+    }
+}
+
+#[test]
+fn test_prefab_out_of_bounds() {
+    let mut description = common::road_description(5, 5).into_3d();
+    description.prefabs = vec![PrefabPlacement::new(
+        street(),
+        Some(Point::new(3, 0, 1)),
+        Some(TileRotation::R0),
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    assert_eq!(
+        Some(ProblemError::PrefabOutOfBounds(0)),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+#[test]
+fn test_prefab_chosen_placement() {
+    let mut description = common::road_description(5, 5).into_3d();
+    description.prefabs = vec![PrefabPlacement::new(street(), None, None)];
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        let ends = solution
+            .grid
+            .points()
+            .filter(|p| solution.tile(*p).unwrap().id == "end")
+            .count();
+        assert!(ends >= 2);
+    }
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    description.dimensions = Dimensions::new(2, 1, 2);
+    assert_eq!(
+        Some(ProblemError::PrefabUnplaceable(0)),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+/// A short street along x, closed off at both ends.
+fn street() -> Prefab<&'static str> {
+    let mut prefab = Prefab::new(Dimensions::new(3, 1, 1));
+    prefab.set(Point::new(0, 0, 0), Some(("end", TileRotation::R180)));
+    prefab.set(Point::new(1, 0, 0), Some(("road", TileRotation::R0)));
+    prefab.set(Point::new(2, 0, 0), Some(("end", TileRotation::R0)));
+    prefab
+}
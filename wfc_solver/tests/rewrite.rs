@@ -0,0 +1,111 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{MetadataValue, Prefab, PrefabPlacement, TileRotation, TileSelector},
+    description2d::Tile2d,
+    error::ProblemError,
+    rewrite::RewriteRule,
+    utils::{Dimensions, Direction, Point},
+};
+
+#[test]
+fn test_rewrite() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut solution = wfc_solver::solve(&mut rng, street_description()).unwrap();
+    assert!(solution
+        .placements()
+        .all(|(point, _)| solution.tile(point).unwrap().id != "cap"));
+
+    let mut rule = RewriteRule::new(TileSelector::Tile("end"));
+    rule.neighbours = vec![(Direction::Right, TileSelector::Tile("grass"))];
+    rule.replace = Some("cap");
+    rule.metadata = [("lamp".to_string(), MetadataValue::Bool(true))]
+        .into_iter()
+        .collect();
+    assert!(solution.rewrite(&[rule]).unwrap() >= 2);
+
+    for (x, rotation) in [(1, TileRotation::R180), (3, TileRotation::R0)] {
+        let point = Point::new(x, 0, 2);
+        assert_eq!("cap", solution.tile(point).unwrap().id);
+        assert_eq!(rotation, solution.tile_data(point).unwrap().rotation);
+        let metadata = solution.cell_metadata(point).unwrap();
+        assert_eq!(Some(true), metadata["lamp"].as_bool());
+    }
+    assert!(solution
+        .cell_metadata(Point::new(2, 0, 2))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_rewrite_unknown_tile() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut solution = wfc_solver::solve(&mut rng, street_description()).unwrap();
+
+    let mut annotate = RewriteRule::new(TileSelector::Tile("end"));
+    annotate.replace = Some("cap");
+    annotate.metadata = [("lamp".to_string(), MetadataValue::Bool(true))]
+        .into_iter()
+        .collect();
+    let mut rule = RewriteRule::new(TileSelector::Tile("road"));
+    rule.replace = Some("bridge");
+    assert_eq!(
+        Err(ProblemError::UnknownRewriteTile(1)),
+        solution.rewrite(&[annotate, rule])
+    );
+
+    // Nothing of the first rule is kept.
+    let point = Point::new(1, 0, 2);
+    assert_eq!("end", solution.tile(point).unwrap().id);
+    assert!(solution.cell_metadata(point).unwrap().is_empty());
+}
+
+#[test]
+fn test_rewrite_unplaceable_tile() {
+    // No tile connects to a river, so the solver could never place a bridge.
+    let mut description = street_description();
+    description.connections.push("river".into());
+    description.tiles.push(
+        Tile2d {
+            decorative: true,
+            ..common::tile2d("bridge", ["road", "road", "river", "river"])
+        }
+        .into_tile(),
+    );
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut solution = wfc_solver::solve(&mut rng, description).unwrap();
+    let mut rule = RewriteRule::new(TileSelector::Tile("road"));
+    rule.replace = Some("bridge");
+    assert!(solution.rewrite(&[rule]).unwrap() >= 1);
+    assert_eq!("bridge", solution.tile(Point::new(2, 0, 2)).unwrap().id);
+}
+
+/// A street from (1, 0, 2) to (3, 0, 2) with grass on both ends. "cap" is decorative, so the
+/// solver never picks it.
+fn street_description() -> wfc_solver::description::ProblemDescription<&'static str> {
+    let mut street = Prefab::new(Dimensions::new(5, 1, 1));
+    for (x, id, rotation) in [
+        (0, "grass", TileRotation::R0),
+        (1, "end", TileRotation::R180),
+        (2, "road", TileRotation::R0),
+        (3, "end", TileRotation::R0),
+        (4, "grass", TileRotation::R0),
+    ] {
+        street.set(Point::new(x, 0, 0), Some((id, rotation)));
+    }
+
+    let mut description = common::road_description(5, 5);
+    description.tiles.push(Tile2d {
+        decorative: true,
+        ..common::tile2d("cap", ["road", "grass", "grass", "grass"])
+    });
+    let mut description = description.into_3d();
+    description.prefabs = vec![PrefabPlacement::new(
+        street,
+        Some(Point::new(0, 0, 2)),
+        Some(TileRotation::R0),
+    )];
+    description
+}
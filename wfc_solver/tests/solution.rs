@@ -0,0 +1,611 @@
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{
+        Amount, Axis, BorderRule, ConnectivityConstraint, CountConstraint, FixedTile, Gradient,
+        HeightGuide, HorizontalConnection, MetadataValue, Placement, ProblemDescription, Tile,
+        TileRotation, TileSelector, VerticalConnection, WeightField, WeightMap, Zone, ZoneMap,
+    },
+    error::{Contradiction, ProblemError, SolveStage},
+    utils::{Dimensions, Direction, Heightmap, Mask, Point, ZoneLayout},
+    validation::ValidationIssue,
+};
+
+#[test]
+fn test_metadata_in_solution() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, air_description()).unwrap();
+
+    let metadata = solution.metadata(Point::new(1, 1, 1)).unwrap();
+
+    assert_eq!(Some(true), metadata["walkable"].as_bool());
+    assert_eq!(Some(1.5), metadata["cost"].as_f64());
+    assert_eq!(Some("air"), solution.tile(Point::new(0, 0, 0)).map(|t| t.id));
+}
+
+#[test]
+fn test_zero_weight_never_chosen() {
+    let mut description = air_description();
+    let mut heavy = description.tiles[0].clone();
+    heavy.id = "heavy";
+    description.tiles[0].weight = 0.0;
+    description.tiles.push(heavy);
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(7);
+    let solution = wfc_solver::solve(&mut rng, description).unwrap();
+
+    for x in 0..2 {
+        for y in 0..2 {
+            for z in 0..2 {
+                let tile = solution.tile(Point::new(x, y, z)).unwrap();
+                assert_eq!("heavy", tile.id);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_invalid_dimensions() {
+    let mut description = air_description();
+    description.dimensions = Dimensions::new(2, 0, 2);
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let result = wfc_solver::solve(&mut rng, description);
+
+    assert_eq!(
+        Some(ProblemError::Dimensions(Dimensions::new(2, 0, 2))),
+        result.err()
+    );
+}
+
+#[test]
+fn test_invalid_fixed_tiles() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+
+    let mut description = air_description();
+    description.fixed = vec![FixedTile::new(Point::new(0, 2, 0), TileRotation::R0, "air")];
+    assert_eq!(
+        Some(ProblemError::FixedOutOfBounds(Point::new(0, 2, 0))),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+
+    let mut description = air_description();
+    description.fixed = vec![FixedTile::new(Point::new(0, 1, 0), TileRotation::R0, "stone")];
+    assert_eq!(
+        Some(ProblemError::UnknownFixedTile(Point::new(0, 1, 0))),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+#[test]
+fn test_fixed_tile_contradiction() {
+    let mut description = air_description();
+    let mut solid = description.tiles[0].clone();
+    solid.id = "solid";
+    for face in [
+        &mut solid.left,
+        &mut solid.right,
+        &mut solid.forward,
+        &mut solid.backward,
+    ] {
+        face[0].connection = "solid".into();
+    }
+    description.tiles.push(solid);
+    description.fixed = vec![
+        FixedTile::new(Point::new(0, 0, 0), TileRotation::R0, "air"),
+        FixedTile::new(Point::new(1, 0, 0), TileRotation::R0, "solid"),
+    ];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let result = wfc_solver::solve(&mut rng, description);
+
+    assert_eq!(
+        Some(ProblemError::Contradiction(Contradiction {
+            point: Point::new(1, 0, 0),
+            stage: SolveStage::FixedTiles,
+            cause: None,
+        })),
+        result.err()
+    );
+}
+
+#[test]
+fn test_count_constraints() {
+    let mut description = two_tile_description();
+    description.counts = vec![CountConstraint::new(
+        TileSelector::Tile("heavy"),
+        Some(Amount::Cells(1)),
+        Some(Amount::Cells(1)),
+    )];
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        assert_eq!(1, count_tiles(&solution, "heavy"));
+    }
+
+    description.tiles[1].weight = 0.01;
+    description.counts = vec![CountConstraint::new(
+        TileSelector::Tag("dense".into()),
+        Some(Amount::Fraction(0.5)),
+        None,
+    )];
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        assert!(count_tiles(&solution, "heavy") >= 4);
+    }
+}
+
+#[test]
+fn test_count_constraint_violation() {
+    let mut description = two_tile_description();
+    description.counts = vec![CountConstraint::new(
+        TileSelector::Tile("heavy"),
+        Some(Amount::Cells(9)),
+        None,
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let result = wfc_solver::solve(&mut rng, description);
+
+    match result {
+        Err(ProblemError::Count(violation)) => assert_eq!("Tile(\"heavy\")", violation.selector),
+        _ => panic!("Expected a count violation"),
+    }
+}
+
+#[test]
+fn test_connectivity_constraint() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(4, 1, 4);
+    description.tiles[1].weight = 1.0;
+    description.connectivity = vec![ConnectivityConstraint::new(
+        TileSelector::Tile("air"),
+        vec![Point::new(0, 0, 0), Point::new(3, 0, 3)],
+    )];
+
+    for seed in 0..10 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+
+        let dimensions = solution.grid.dimensions();
+        let air = dimensions
+            .points()
+            .filter(|p| solution.tile(*p).unwrap().id == "air")
+            .collect::<Vec<_>>();
+
+        let mut reached = vec![Point::new(0, 0, 0)];
+        let mut index = 0;
+        while index < reached.len() {
+            let point = reached[index];
+            for direction in Direction::ALL {
+                if let Some(neighbour) = point.neighbour(direction, dimensions) {
+                    if air.contains(&neighbour) && !reached.contains(&neighbour) {
+                        reached.push(neighbour);
+                    }
+                }
+            }
+            index += 1;
+        }
+
+        assert_eq!(air.len(), reached.len());
+        assert!(reached.contains(&Point::new(3, 0, 3)));
+    }
+}
+
+#[test]
+fn test_connectivity_forces_cut_cells() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(3, 1, 3);
+    description.mask = Some(Mask::from_fn(description.dimensions, |p| {
+        p.z() == 1 || p.x() == 1
+    }));
+    description.connectivity = vec![ConnectivityConstraint::new(
+        TileSelector::Tile("air"),
+        vec![Point::new(0, 0, 1), Point::new(1, 0, 0)],
+    )];
+
+    for seed in 0..10 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        assert_eq!("air", solution.tile(Point::new(1, 0, 1)).unwrap().id);
+    }
+}
+
+#[test]
+fn test_connectivity_disconnected() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(3, 1, 1);
+    description.fixed = vec![FixedTile::new(Point::new(1, 0, 0), TileRotation::R0, "heavy")];
+    description.connectivity = vec![ConnectivityConstraint::new(
+        TileSelector::Tile("air"),
+        vec![Point::new(0, 0, 0), Point::new(2, 0, 0)],
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let result = wfc_solver::solve(&mut rng, description);
+
+    match result {
+        Err(ProblemError::Disconnected(disconnection)) => {
+            assert_eq!("Tile(\"air\")", disconnection.selector)
+        }
+        _ => panic!("Expected a disconnection"),
+    }
+}
+
+#[test]
+fn test_placement_rules() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(3, 3, 3);
+    description.tiles[1].placement = Placement {
+        max_y: Some(0),
+        border: Some(BorderRule::Never),
+        ..Default::default()
+    };
+    description.tiles[0].placement = Placement {
+        min_y: Some(1),
+        ..Default::default()
+    };
+    let mut third = description.tiles[0].clone();
+    third.id = "third";
+    third.placement = Placement {
+        max_y: Some(0),
+        border: Some(BorderRule::Only),
+        ..Default::default()
+    };
+    description.tiles.push(third);
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+    for point in solution.grid.dimensions().points() {
+        let expected = if point == Point::new(1, 0, 1) {
+            "heavy"
+        } else if point.y() == 0 {
+            "third"
+        } else {
+            "air"
+        };
+        assert_eq!(expected, solution.tile(point).unwrap().id);
+    }
+
+    description.tiles[2].placement.border = Some(BorderRule::Never);
+    let result = wfc_solver::solve(&mut rng, description);
+    assert!(matches!(
+        result,
+        Err(ProblemError::Contradiction(Contradiction {
+            stage: SolveStage::Placement,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_mask() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(3, 2, 3);
+    let mask = Mask::from_fn(description.dimensions, |p| p.x() < 2 || p.z() < 2);
+    description.mask = Some(mask.clone());
+    description.counts = vec![CountConstraint::new(
+        TileSelector::Tile("air"),
+        Some(Amount::Fraction(1.0)),
+        None,
+    )];
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+    for point in solution.grid.dimensions().points() {
+        if mask.is_active(point) {
+            assert_eq!("air", solution.tile(point).unwrap().id);
+        } else {
+            assert!(solution.tile(point).is_none());
+        }
+    }
+
+    description.fixed = vec![FixedTile::new(Point::new(2, 0, 2), TileRotation::R0, "air")];
+    assert_eq!(
+        Some(ProblemError::FixedOutOfBounds(Point::new(2, 0, 2))),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+#[test]
+fn test_mask_tile() {
+    // "cliff" can only face the "rock" mask tile, not another cliff, and rock itself can never
+    // be placed.
+    let mut description = air_description();
+    description.dimensions = Dimensions::new(3, 1, 3);
+    description
+        .connections
+        .extend(["rock".into(), "void".into()]);
+    let mut rock = description.tiles[0].clone();
+    rock.id = "rock";
+    for face in [
+        &mut rock.left,
+        &mut rock.right,
+        &mut rock.forward,
+        &mut rock.backward,
+    ] {
+        face[0] = HorizontalConnection {
+            connection: "rock".into(),
+            flipped: true,
+            symmetry: false,
+        };
+    }
+    rock.up[0].connection = "void".into();
+    let mut cliff = description.tiles[0].clone();
+    cliff.id = "cliff";
+    cliff.forward[0] = HorizontalConnection {
+        connection: "rock".into(),
+        flipped: false,
+        symmetry: false,
+    };
+    description.tiles.extend([rock, cliff]);
+    description.mask = Some(Mask::from_fn(description.dimensions, |p| {
+        p != Point::new(1, 0, 1)
+    }));
+    description.mask_tile = Some("rock");
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, description).unwrap();
+    for point in [
+        Point::new(0, 0, 1),
+        Point::new(2, 0, 1),
+        Point::new(1, 0, 0),
+        Point::new(1, 0, 2),
+    ] {
+        assert_eq!("cliff", solution.tile(point).unwrap().id);
+    }
+}
+
+#[test]
+fn test_footprint() {
+    let mut description = air_description();
+    description.dimensions = Dimensions::new(3, 2, 3);
+    let mut wide = description.tiles[0].clone();
+    wide.id = "wide";
+    wide.footprint = (2, 1, 1);
+    description.tiles.push(wide);
+    description.fixed = vec![FixedTile::new(
+        Point::new(0, 0, 0),
+        TileRotation::R0,
+        "wide",
+    )];
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        assert_eq!(
+            Some(Point::new(1, 0, 0)),
+            solution
+                .tile_data(Point::new(1, 0, 0))
+                .map(|data| data.part)
+        );
+
+        let mut covered = 0;
+        for (anchor, data) in solution.placements() {
+            let tile = &solution.description.tiles[data.tile_index];
+            for part in tile.rotated_footprint(data.rotation).points() {
+                let point = Point::new(
+                    anchor.x() + part.x(),
+                    anchor.y() + part.y(),
+                    anchor.z() + part.z(),
+                );
+                let other = solution.tile_data(point).unwrap();
+                assert_eq!(
+                    (data.tile_index, data.rotation),
+                    (other.tile_index, other.rotation)
+                );
+                assert_eq!(part, other.part);
+                covered += 1;
+            }
+        }
+        assert_eq!(solution.grid.dimensions().len(), covered);
+    }
+}
+
+#[test]
+fn test_footprint_counts_once() {
+    let mut description = air_description();
+    description.dimensions = Dimensions::new(3, 1, 3);
+    let mut wide = description.tiles[0].clone();
+    wide.id = "wide";
+    wide.footprint = (2, 1, 1);
+    description.tiles.push(wide);
+    description.counts = vec![CountConstraint::new(
+        TileSelector::Tile("wide"),
+        Some(Amount::Cells(1)),
+        Some(Amount::Cells(1)),
+    )];
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        let wide = solution
+            .placements()
+            .filter(|(_, data)| data.tile_index == 1)
+            .count();
+        assert_eq!(1, wide);
+        // One wide tile and seven single cells of air.
+        assert_eq!(1.0 / 8.0, solution.histogram()[1].1);
+    }
+}
+
+#[test]
+fn test_weight_gradient() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(3, 1, 3);
+    description.weight_fields = vec![WeightField::new(
+        TileSelector::Tag("dense".into()),
+        WeightMap::Gradient(Gradient::Linear {
+            axis: Axis::X,
+            from: 1.0,
+            to: 0.0,
+        }),
+    )];
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        for z in 0..3 {
+            assert_eq!("air", solution.tile(Point::new(2, 0, z)).unwrap().id);
+        }
+    }
+
+    let negative = WeightMap::Gradient(Gradient::Linear {
+        axis: Axis::X,
+        from: 1.0,
+        to: -1.0,
+    });
+    assert_eq!(
+        0.0,
+        negative.factor(Point::new(2, 0, 0), description.dimensions)
+    );
+
+    description.weight_fields = vec![WeightField::new(
+        TileSelector::Tag("dense".into()),
+        WeightMap::from_fn(Dimensions::new(2, 2, 2), |_| 1.0),
+    )];
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    assert_eq!(
+        Some(ProblemError::WeightFieldDimensions(Dimensions::new(
+            2, 2, 2
+        ))),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+#[test]
+fn test_height_guide() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(3, 3, 2);
+    description.height_guide = Some(HeightGuide::new(
+        Heightmap::from_fn(3, 2, |x, _| x),
+        vec![TileSelector::Tag("dense".into())],
+        vec![TileSelector::Tile("air")],
+    ));
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+    for point in solution.grid.dimensions().points() {
+        let expected = if point.y() < point.x() {
+            "heavy"
+        } else {
+            "air"
+        };
+        assert_eq!(expected, solution.tile(point).unwrap().id);
+    }
+
+    description.height_guide.as_mut().unwrap().heightmap = Heightmap::new(2, 2);
+    assert!(description
+        .validate()
+        .contains(&ValidationIssue::InvalidDescription(
+            ProblemError::HeightmapDimensions(2, 2)
+        )));
+    assert_eq!(
+        Some(ProblemError::HeightmapDimensions(2, 2)),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+#[test]
+fn test_zones() {
+    let mut description = two_tile_description();
+    description.dimensions = Dimensions::new(4, 1, 2);
+    let layout = ZoneLayout::from_fn(description.dimensions, |p| (p.x() >= 2) as usize);
+    description.zones = Some(ZoneMap::new(
+        vec![
+            Zone::new("park", vec![TileSelector::Tile("air")]),
+            Zone::new("market", vec![TileSelector::Tag("dense".into())]),
+        ],
+        layout.clone(),
+        vec![TileSelector::Tile("air")],
+    ));
+
+    for seed in 0..5 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        for z in 0..2 {
+            assert_eq!("air", solution.tile(Point::new(1, 0, z)).unwrap().id);
+            assert_eq!("heavy", solution.tile(Point::new(3, 0, z)).unwrap().id);
+        }
+    }
+
+    let mut layout = layout;
+    layout.set(Point::new(0, 0, 1), 2);
+    description.zones.as_mut().unwrap().layout = layout;
+    assert!(description
+        .compile()
+        .placement(Point::new(0, 0, 1))
+        .not_any());
+    assert!(description
+        .validate()
+        .contains(&ValidationIssue::InvalidDescription(
+            ProblemError::UnknownZone(Point::new(0, 0, 1))
+        )));
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    assert_eq!(
+        Some(ProblemError::UnknownZone(Point::new(0, 0, 1))),
+        wfc_solver::solve(&mut rng, description).err()
+    );
+}
+
+fn count_tiles(solution: &wfc_solver::Solution<&'static str>, id: &str) -> usize {
+    solution
+        .grid
+        .dimensions()
+        .points()
+        .filter(|p| solution.tile(*p).unwrap().id == id)
+        .count()
+}
+
+/// Two interchangeable tiles, "air" and the much more likely "heavy".
+fn two_tile_description() -> ProblemDescription<&'static str> {
+    let mut description = air_description();
+    let mut heavy = description.tiles[0].clone();
+    heavy.id = "heavy";
+    heavy.weight = 100.0;
+    heavy.tags = vec!["dense".into()];
+    description.tiles.push(heavy);
+    description
+}
+
+fn air_description() -> ProblemDescription<&'static str> {
+    let side = vec![HorizontalConnection {
+        connection: "air".into(),
+        flipped: false,
+        symmetry: true,
+    }];
+
+    ProblemDescription {
+        connections: vec!["air".into()],
+        dimensions: Dimensions::new(2, 2, 2),
+        tiles: vec![Tile {
+            id: "air",
+            can_flip: false,
+            can_rotate: true,
+            up: vec![VerticalConnection {
+                connection: "air".into(),
+            }],
+            down: vec![VerticalConnection {
+                connection: "air".into(),
+            }],
+            left: side.clone(),
+            right: side.clone(),
+            forward: side.clone(),
+            backward: side,
+            weight: 1.0,
+            metadata: [
+                ("walkable".to_string(), MetadataValue::Bool(true)),
+                ("cost".to_string(), MetadataValue::Float(1.5)),
+            ]
+            .into_iter()
+            .collect(),
+            tags: vec!["open".into()],
+            placement: Placement::default(),
+            footprint: (1, 1, 1),
+            decorative: false,
+        }],
+        ..Default::default()
+    }
+}
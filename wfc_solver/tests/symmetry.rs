@@ -0,0 +1,122 @@
+mod common;
+
+use rand::SeedableRng;
+use wfc_solver::{
+    description::{HorizontalConnection, Symmetry, TileRotation},
+    error::ProblemError,
+    utils::{Dimensions, Direction},
+};
+
+#[test]
+fn test_mirror_symmetry() {
+    for seed in 0..4 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let description = road_description(6, 5, vec![Symmetry::MirrorX, Symmetry::MirrorZ]);
+        let solution = wfc_solver::solve(&mut rng, description).unwrap();
+
+        let dimensions = solution.grid.dimensions();
+        for point in dimensions.points() {
+            let tile = solution.tile(point).unwrap();
+            let rotation = solution.tile_data(point).unwrap().rotation;
+            for symmetry in [Symmetry::MirrorX, Symmetry::MirrorZ] {
+                let image = symmetry.image(point, dimensions);
+                let other = solution.tile(image).unwrap();
+                let other_rotation = solution.tile_data(image).unwrap().rotation;
+                assert_eq!(tile.id, other.id);
+
+                for direction in [Direction::Left, Direction::Forward] {
+                    let mirrored = match (symmetry, direction) {
+                        (Symmetry::MirrorX, Direction::Left) => Direction::Right,
+                        (Symmetry::MirrorZ, Direction::Forward) => Direction::Backward,
+                        _ => direction,
+                    };
+                    assert_eq!(
+                        tile.rotated_connections(direction, rotation),
+                        other.rotated_connections(mirrored, other_rotation)
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rotation_symmetry() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+    let description = road_description(6, 6, vec![Symmetry::Rotate90]);
+    let solution = wfc_solver::solve(&mut rng, description).unwrap();
+
+    let dimensions = solution.grid.dimensions();
+    for point in dimensions.points() {
+        let image = Symmetry::Rotate90.image(point, dimensions);
+        assert_eq!(
+            solution.tile(point).unwrap().id,
+            solution.tile(image).unwrap().id
+        );
+        assert_eq!(
+            solution.tile_data(point).unwrap().rotation + TileRotation::R90,
+            solution.tile_data(image).unwrap().rotation
+        );
+    }
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+    assert_eq!(
+        Err(ProblemError::SymmetryDimensions(Dimensions::new(6, 1, 5))),
+        wfc_solver::solve(&mut rng, road_description(6, 5, vec![Symmetry::Rotate90])).map(|_| ())
+    );
+}
+
+#[test]
+fn test_mirror_pairs() {
+    let mut description = road_description(5, 5, vec![Symmetry::MirrorX]);
+    description.connections.push("fence".into());
+    // A fence only shows its mirrored side on the other tile, no rotation of the same tile does.
+    for (id, flipped) in [("fence", false), ("fence_mirrored", true)] {
+        let mut fence = description.tiles[0].clone();
+        fence.id = id;
+        fence.forward = vec![HorizontalConnection {
+            connection: "fence".into(),
+            flipped,
+            symmetry: false,
+        }];
+        description.tiles.push(fence);
+    }
+
+    let compiled = description.compile();
+    for (variant, image) in compiled.symmetry[0].images.iter().enumerate() {
+        let tile = description.tiles[compiled.data(variant).tile_index].id;
+        let image = image.map(|image| description.tiles[compiled.data(image).tile_index].id);
+        let expected = match tile {
+            "fence" => "fence_mirrored",
+            "fence_mirrored" => "fence",
+            _ => tile,
+        };
+        assert_eq!(Some(expected), image);
+    }
+
+    for seed in 0..4 {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let solution = wfc_solver::solve(&mut rng, description.clone()).unwrap();
+        let dimensions = solution.grid.dimensions();
+        for point in dimensions.points() {
+            let image = Symmetry::MirrorX.image(point, dimensions);
+            if solution.tile(point).unwrap().id == "fence" {
+                assert_eq!("fence_mirrored", solution.tile(image).unwrap().id);
+            }
+        }
+    }
+}
+
+/// The shared roads with corners.
+fn road_description(
+    width: usize,
+    height: usize,
+    symmetry: Vec<Symmetry>,
+) -> wfc_solver::description::ProblemDescription<&'static str> {
+    let mut description = common::road_description(width, height);
+    description
+        .tiles
+        .push(common::tile2d("corner", ["road", "grass", "road", "grass"]));
+    description.symmetry = symmetry;
+    description.into_3d()
+}
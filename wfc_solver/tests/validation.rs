@@ -0,0 +1,87 @@
+mod common;
+
+use common::basic_straight_air_description;
+use wfc_solver::{
+    utils::{Dimensions, Direction},
+    validation::ValidationIssue,
+};
+
+#[test]
+fn test_validate_unmatched_faces() {
+    let desc = basic_straight_air_description();
+
+    let issues = desc.validate();
+
+    assert_eq!(
+        vec![
+            ValidationIssue::UnmatchedFace {
+                tile: 1,
+                face: Direction::Down
+            },
+            ValidationIssue::UnmatchedFace {
+                tile: 2,
+                face: Direction::Down
+            },
+        ],
+        issues
+    );
+}
+
+#[test]
+fn test_validate_duplicates_and_sockets() {
+    let mut desc = basic_straight_air_description();
+    desc.connections.retain(|c| c != "full");
+    desc.tiles[2].id = 1;
+
+    let issues = desc.validate();
+
+    assert!(issues.contains(&ValidationIssue::DuplicateTileId { tile: 1, count: 2 }));
+    assert!(issues.contains(&ValidationIssue::UndeclaredSocket {
+        tile: 1,
+        face: Direction::Down,
+        connection: "full".into()
+    }));
+}
+
+#[test]
+fn test_validate_asymmetric_sockets() {
+    // With every half socket facing the same way, no two of them connect.
+    let mut desc = basic_straight_air_description();
+    desc.tiles[1].right[0].flipped = false;
+    desc.tiles[2].forward[0].flipped = false;
+
+    let issues = desc.validate();
+
+    assert!(issues.contains(&ValidationIssue::AsymmetricSocket {
+        tile: 1,
+        face: Direction::Left,
+        connection: "half".into()
+    }));
+    assert!(issues
+        .iter()
+        .any(|issue| issue.to_string().contains("no flipped counterpart")));
+
+    // A symmetric half socket connects to the others in any orientation.
+    let mut desc = basic_straight_air_description();
+    desc.tiles[1].right[0].symmetry = true;
+
+    assert!(desc
+        .validate()
+        .contains(&ValidationIssue::AsymmetricSocket {
+            tile: 2,
+            face: Direction::Forward,
+            connection: "half".into()
+        }));
+}
+
+#[test]
+fn test_validate_never_placeable() {
+    let mut desc = basic_straight_air_description();
+    desc.dimensions = Dimensions::new(1, 1, 1);
+
+    let issues = desc.validate();
+
+    assert!(issues.contains(&ValidationIssue::NeverPlaceable { tile: 1 }));
+    assert!(issues.contains(&ValidationIssue::NeverPlaceable { tile: 2 }));
+    assert!(!issues.contains(&ValidationIssue::NeverPlaceable { tile: 0 }));
+}